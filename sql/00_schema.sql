@@ -34,9 +34,21 @@ CREATE TABLE IF NOT EXISTS t_key_meta (
     "AES_256",
     "RSA_2048",
     "RSA_3072",
+    "RSA_4096",
     "EC_P256",
     "EC_P256k",
-    "SM4"
+    "EC_P384",
+    "EC_P521",
+    "EC_SM2",
+    "SM4",
+    "ED25519",
+    "ED448",
+    "X25519",
+    "X448",
+    "HMAC_SHA256",
+    "HMAC_SHA384",
+    "HMAC_SHA512",
+    "HMAC_SM3"
   ) NOT NULL COMMENT "密钥规格",
   origin ENUM("KMS", "EXTERNAL") NOT NULL COMMENT "密钥来源，0: kms 创建，1: 密钥材料导入",
   description TEXT COMMENT "密钥描述",
@@ -46,12 +58,13 @@ CREATE TABLE IF NOT EXISTS t_key_meta (
     "PENDING_DELETION",
     "PENDING_IMPORT"
   ) NOT NULL COMMENT "密钥状态, 0: enable，1: disable，2: pending_deletion，3: pending_import",
-  `usage` ENUM("ENCRYPT/DECRYPT", "SIGN/VERIFY") NOT NULL COMMENT "密钥用途，0: encrypt/decrypt，1: sign/verify",
+  `usage` ENUM("ENCRYPT/DECRYPT", "SIGN/VERIFY", "GENERATE/VERIFY_MAC", "KEY_AGREEMENT") NOT NULL COMMENT "密钥用途，0: encrypt/decrypt，1: sign/verify，2: generate/verify mac，3: key agreement",
   `version` VARCHAR(32) NOT NULL COMMENT "密钥版本",
   primary_version VARCHAR(32) NOT NULL COMMENT "主密钥版本",
   creator VARCHAR(32) NOT NULL COMMENT "密钥创建者",
   rotation_interval BIGINT NOT NULL COMMENT "密钥轮换周期，开启轮换 > 0，不开启为 -1",
   material_expire_at DATETIME COMMENT "密钥材料过期时间",
+  material_fingerprint VARCHAR(64) COMMENT "导入密钥材料指纹，仅允许重新导入相同的密钥材料",
   last_rotation_at DATETIME COMMENT "密钥上次轮换事件，为 null 表示未发生过轮换",
   deletion_at DATETIME COMMENT "密钥预计删除时间 null 表示不删除",
   updated_at DATETIME NOT NULL DEFAULT NOW() ON UPDATE CURRENT_TIMESTAMP,
@@ -70,4 +83,35 @@ CREATE TABLE IF NOT EXISTS t_key_alias (
   PRIMARY KEY(_id),
   INDEX idx_key_id(key_id),
   UNIQUE uniq_key_alias(`alias`)
-)
\ No newline at end of file
+);
+
+CREATE TABLE IF NOT EXISTS t_key_history (
+  _id BIGINT NOT NULL AUTO_INCREMENT,
+  key_id VARCHAR(32) NOT NULL COMMENT "主密钥标识",
+  action ENUM("IMPORT_KEY_MATERIAL") NOT NULL COMMENT "密钥操作",
+  success BOOLEAN NOT NULL COMMENT "操作是否成功",
+  detail TEXT COMMENT "操作失败原因",
+  created_at DATETIME NOT NULL DEFAULT NOW(),
+  PRIMARY KEY(_id),
+  INDEX idx_key_id(key_id)
+);
+
+CREATE TABLE IF NOT EXISTS t_certificate (
+  _id BIGINT NOT NULL AUTO_INCREMENT,
+  key_id VARCHAR(32) NOT NULL COMMENT "签发密钥标识",
+  `version` VARCHAR(32) NOT NULL COMMENT "签发密钥版本",
+  serial VARCHAR(64) NOT NULL COMMENT "证书序列号，十六进制",
+  subject VARCHAR(1024) NOT NULL COMMENT "证书主体",
+  issuer VARCHAR(1024) NOT NULL COMMENT "证书签发者",
+  public_key_fingerprint VARCHAR(64) NOT NULL COMMENT "证书公钥 SHA-256 指纹",
+  ca BOOLEAN NOT NULL COMMENT "是否为 CA 证书",
+  not_before DATETIME NOT NULL COMMENT "证书生效时间",
+  not_after DATETIME NOT NULL COMMENT "证书失效时间",
+  certificate TEXT NOT NULL COMMENT "PEM 编码的证书",
+  chain TEXT NOT NULL COMMENT "PEM 编码的签发者证书链，自签名证书为空",
+  created_at DATETIME NOT NULL DEFAULT NOW(),
+  PRIMARY KEY(_id),
+  INDEX idx_key_id(key_id),
+  INDEX idx_public_key_fingerprint(public_key_fingerprint),
+  UNIQUE uniq_key_serial(key_id, serial)
+);
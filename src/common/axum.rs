@@ -1,15 +1,27 @@
 use axum::{
     extract::{
         rejection::{FormRejection, JsonRejection, QueryRejection},
-        FromRequest, FromRequestParts,
+        FromRequest, FromRequestParts, Request,
     },
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
 };
 use chrono::{DateTime, FixedOffset, Local};
+use openssl::memcmp;
 use serde::Serialize;
 use serde_json::json;
 
+use crate::{
+    common::{
+        configs::env_var_default,
+        errors::{Result, ServiceError},
+    },
+    crypto::root_key,
+};
+
+const SYS_OPERATOR_TOKEN: &str = "SYS_OPERATOR_TOKEN";
+
 #[derive(FromRequest)]
 #[from_request(via(axum::Json), rejection(ErrorResponse))]
 pub struct Json<T>(pub T);
@@ -93,3 +105,38 @@ impl IntoResponse for ErrorResponse {
             .into_response()
     }
 }
+
+// rejects requests touching key material while the root key is sealed
+pub async fn require_unsealed(
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    if root_key::is_sealed() {
+        return Err(ServiceError::Sealed("root key is sealed".to_owned()));
+    }
+    Ok(next.run(request).await)
+}
+
+// guards the operator endpoints by a bearer token, an unset token rejects
+// every request instead of leaving the endpoints open
+pub async fn require_operator(
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let expected = env_var_default::<String>(SYS_OPERATOR_TOKEN, String::new());
+    let actual = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if expected.is_empty()
+        || expected.len() != actual.len()
+        || !memcmp::eq(expected.as_bytes(), actual.as_bytes())
+    {
+        return Err(ServiceError::Unauthorized(
+            "operator token is invalid".to_owned(),
+        ));
+    }
+    Ok(next.run(request).await)
+}
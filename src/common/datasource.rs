@@ -43,7 +43,11 @@ pub struct Paginator {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
-#[aliases(PaginatedKeyAliasModels = PaginatedResult<Vec<KeyAliasModel>>)]
+#[aliases(
+    PaginatedKeyAliasModels = PaginatedResult<Vec<KeyAliasModel>>,
+    PaginatedCertificateModels = PaginatedResult<Vec<CertificateModel>>,
+    PaginatedKeyHistoryModels = PaginatedResult<Vec<KeyHistoryModel>>
+)]
 pub struct PaginatedResult<T: Serialize> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub next: Option<String>,
@@ -21,6 +21,8 @@ pub enum ServiceError {
     StateChange(KeyStateStatus),
     #[error("{0}")]
     NotFount(String),
+    #[error("{0}")]
+    Sealed(String),
     #[error("internal server error {0}")]
     InternalServer(#[from] anyhow::Error),
     #[error("datasource error")]
@@ -36,6 +38,7 @@ impl IntoResponse for ServiceError {
             ServiceError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
             ServiceError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
             ServiceError::NotFount(msg) => (StatusCode::NOT_FOUND, msg),
+            ServiceError::Sealed(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
             ServiceError::Unsupported(msg) => (StatusCode::IM_A_TEAPOT, msg),
             ServiceError::InternalServer(e) => {
                 tracing::debug!("error backtrace: {}", e.backtrace());
@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Context};
 use base64::{
-    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE_NO_PAD},
     Engine,
 };
 use ring::rand::{SecureRandom, SystemRandom};
@@ -63,3 +63,14 @@ pub fn decode64_no_padding(source: &str) -> Result<Vec<u8>> {
         .decode(source)
         .context("base64url decode failed".to_string())?)
 }
+
+pub fn encode64_url(source: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(source)
+}
+
+#[cfg(test)]
+pub fn decode64_url(source: &str) -> Result<Vec<u8>> {
+    Ok(URL_SAFE_NO_PAD
+        .decode(source)
+        .context("base64url decode failed".to_string())?)
+}
@@ -1,42 +1,70 @@
 use utoipa::OpenApi;
 
 use crate::{
-    common::datasource::{PaginatedKeyAliasModels, Paginator},
+    common::datasource::{
+        PaginatedCertificateModels, PaginatedKeyAliasModels,
+        PaginatedKeyHistoryModels, Paginator,
+    },
     crypto::types::{
-        KeyOrigin, KeySpec, KeyState, KeyType, KeyUsage, WrappingKeyAlgorithm,
-        WrappingKeySpec,
+        CertificateKeyUsage, ExtendedKeyUsage, KeyAlgorithm, KeyOrigin,
+        KeySpec, KeyState, KeyType, KeyUsage, MessageDigest, MessageType,
+        PublicKeyFormat, WrappingKeyAlgorithm, WrappingKeySpec,
     },
-    entity::prelude::*,
+    entity::{key_history::KeyHistoryAction, prelude::*},
     pojo::{
         form::{
-            key::{KeyCreateBody, KeyImportBody, KeyImportParamsQuery},
+            certificate::{KeyCertificateIssueBody, KeyCsrBody},
+            crypto::{
+                KeyDecryptBody, KeyDeriveSharedSecretBody, KeyEncryptBody,
+                KeyGenerateDataKeyBody, KeyGenerateMacBody, KeyJwsBody,
+                KeyReEncryptBody, KeySignBody, KeyVerifyBody, KeyVerifyMacBody,
+            },
+            key::{
+                KeyCreateBody, KeyImportBody, KeyImportParamsQuery,
+                KeyPublicKeyQuery,
+            },
             key_extra::{
                 KeyAliasCreateOrUpdateForm, KeyAliasDeleteForm,
-                KeyChangeStateBody, KeyMetaPatchForm,
+                KeyChangeStateBody, KeyMetaPatchForm, KeyScheduleDeletionBody,
             },
             kms::{KmsCreateBody, KmsPatchForm},
+            sys::{SysInitBody, SysUnsealBody},
         },
         result::{
+            certificate::{KeyCertificateResult, KeyCsrResult},
+            crypto::{
+                KeyDataKeyResult, KeyDecryptResult, KeyEncryptResult,
+                KeyGenerateMacResult, KeyJwsResult, KeyReEncryptResult,
+                KeySharedSecretResult, KeySignResult, KeyVerifyMacResult,
+                KeyVerifyResult,
+            },
             key::{
                 KeyCreateResult, KeyMaterialImportParamsResult,
-                KeyVersionResult,
+                KeyPublicKeyResult, KeyVersionResult,
             },
-            kms::KmsResult,
+            key_extra::KeyDeletionResult,
+            kms::{JwksResult, KmsResult},
+            sys::{SysInitResult, SysStatusResult},
         },
     },
 };
 
+pub mod certificate_controller;
 pub mod crypto_controller;
 pub mod key_alias_controller;
 pub mod key_controller;
 pub mod key_meta_controller;
 pub mod kms_controller;
+pub mod sys_controller;
 
 #[derive(OpenApi)]
 #[openapi(
     components(schemas(
         KmsModel,
         KeyAliasModel,
+        KeyHistoryModel,
+        KeyHistoryAction,
+        CertificateModel,
         KeyMetaModel,
         KmsResult,
         KmsCreateBody,
@@ -47,43 +75,104 @@ pub mod kms_controller;
         KeyImportBody,
         KeyCreateResult,
         KeyMaterialImportParamsResult,
+        KeyPublicKeyQuery,
+        KeyPublicKeyResult,
         KeyAliasDeleteForm,
         KeyAliasCreateOrUpdateForm,
         KeyVersionResult,
         KeyMetaPatchForm,
+        KeyScheduleDeletionBody,
+        KeyDeletionResult,
+        KeyEncryptBody,
+        KeyEncryptResult,
+        KeyDecryptBody,
+        KeyDecryptResult,
+        KeyReEncryptBody,
+        KeyReEncryptResult,
+        KeyGenerateDataKeyBody,
+        KeyDataKeyResult,
+        KeySignBody,
+        KeySignResult,
+        KeyVerifyBody,
+        KeyVerifyResult,
+        KeyGenerateMacBody,
+        KeyGenerateMacResult,
+        KeyVerifyMacBody,
+        KeyVerifyMacResult,
+        KeyDeriveSharedSecretBody,
+        KeySharedSecretResult,
+        KeyJwsBody,
+        KeyJwsResult,
+        JwksResult,
+        KeyCsrBody,
+        KeyCsrResult,
+        KeyCertificateIssueBody,
+        KeyCertificateResult,
+        SysInitBody,
+        SysUnsealBody,
+        SysInitResult,
+        SysStatusResult,
+        KeyAlgorithm,
+        MessageDigest,
+        MessageType,
         KeyUsage,
         KeyOrigin,
         KeySpec,
         KeyState,
         KeyType,
+        PublicKeyFormat,
+        CertificateKeyUsage,
+        ExtendedKeyUsage,
         WrappingKeyAlgorithm,
         WrappingKeySpec,
         Paginator,
         PaginatedKeyAliasModels,
+        PaginatedKeyHistoryModels,
+        PaginatedCertificateModels,
     )),
     paths(
         kms_controller::create_kms,
         kms_controller::destroy_kms,
         kms_controller::set_kms,
         kms_controller::get_kms,
+        kms_controller::get_jwks,
         key_controller::create_key,
         key_controller::import_key,
         key_controller::import_key_params,
         key_controller::create_key_version,
+        key_controller::delete_key_material,
+        key_controller::get_public_key,
         key_meta_controller::list_kms_keys,
         key_meta_controller::list_key_version,
+        key_meta_controller::list_key_history,
         key_meta_controller::set_key_meta,
         key_meta_controller::get_key_meta,
         key_meta_controller::change_key_state,
+        key_meta_controller::schedule_key_deletion,
+        key_meta_controller::cancel_key_deletion,
         key_alias_controller::set_key_alias,
         key_alias_controller::remove_key_alias,
         key_alias_controller::list_key_alias,
         crypto_controller::encrypt,
         crypto_controller::advance_encrypt,
         crypto_controller::decrypt,
+        crypto_controller::re_encrypt,
+        crypto_controller::generate_data_key,
+        crypto_controller::generate_data_key_without_plaintext,
         crypto_controller::advance_sign,
         crypto_controller::sign,
         crypto_controller::verify,
+        crypto_controller::generate_mac,
+        crypto_controller::verify_mac,
+        crypto_controller::derive_shared_secret,
+        crypto_controller::sign_jws,
+        certificate_controller::create_csr,
+        certificate_controller::issue_certificate,
+        certificate_controller::list_certificates,
+        sys_controller::init,
+        sys_controller::unseal,
+        sys_controller::seal,
+        sys_controller::status,
     )
 )]
 pub struct ApiDoc {}
@@ -0,0 +1,90 @@
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+};
+
+use crate::{
+    common::{
+        axum::{Json, Query},
+        datasource::Paginator,
+        errors::Result,
+    },
+    pojo::form::certificate::{KeyCertificateIssueBody, KeyCsrBody},
+    service::certificate_service,
+    States,
+};
+
+#[utoipa::path(
+  post,
+  path="/csr",
+  operation_id = "使用主密钥主版本生成证书签名请求",
+  context_path= "/keys/{key_id}",
+  params(
+    ("key_id" = String, Path, description="密钥标识"),
+  ),
+  request_body = KeyCsrBody,
+  responses(
+      (status = 200, description = "PEM 编码的 PKCS#10 请求", body = KeyCsrResult, content_type="application/json"),
+      (status = 400, description = "illegal params")
+  ),
+)]
+pub async fn create_csr(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyCsrBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("create csr, key_id: {}, body: {:?}", key_id, body);
+    certificate_service::create_csr(&rd, &db, &key_id, &body)
+        .await
+        .map(axum::Json)
+}
+
+#[utoipa::path(
+  post,
+  path="/certificates/issue",
+  operation_id = "使用主密钥主版本签发证书",
+  context_path= "/keys/{key_id}",
+  params(
+    ("key_id" = String, Path, description="密钥标识"),
+  ),
+  request_body = KeyCertificateIssueBody,
+  responses(
+      (status = 200, description = "PEM 编码的 X.509 证书及其签发者证书链", body = KeyCertificateResult, content_type="application/json"),
+      (status = 400, description = "illegal params")
+  ),
+)]
+pub async fn issue_certificate(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyCertificateIssueBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("issue certificate, key_id: {}, body: {:?}", key_id, body);
+    certificate_service::issue_certificate(&rd, &db, &key_id, &body)
+        .await
+        .map(axum::Json)
+}
+
+#[utoipa::path(
+    get,
+    path="/certificates",
+    operation_id = "密钥签发证书的分页查询",
+    context_path= "/keys/{key_id}",
+    params(
+        ("key_id" = String, Path, description="密钥标识"),
+        Paginator
+    ),
+    responses(
+        (status = 200, description = "", body = PaginatedCertificateModels),
+        (status = 400, description = "illegal params")
+    ),
+  )]
+pub async fn list_certificates(
+    State(States { db, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Query(paginator): Query<Paginator>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("paging certificates, key_id: {}, {:?}", key_id, paginator);
+    certificate_service::list_certificates(&db, &key_id, paginator)
+        .await
+        .map(axum::Json)
+}
@@ -1,29 +1,48 @@
-use axum::response::IntoResponse;
-use pojo::form::crypto::KeyEncryptBody;
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+};
 
 use crate::{
     common::{axum::Json, errors::Result},
-    pojo,
+    pojo::form::crypto::{
+        KeyDecryptBody, KeyDeriveSharedSecretBody, KeyEncryptBody,
+        KeyGenerateDataKeyBody, KeyGenerateMacBody, KeyJwsBody,
+        KeyReEncryptBody, KeySignBody, KeyVerifyBody, KeyVerifyMacBody,
+    },
+    service::crypto_service,
+    States,
 };
 
 #[utoipa::path(
   post,
-  path="/encrypt",
-  operation_id = "使用默认密钥加密",
+  path="/encrypt/{version}",
+  operation_id = "使用指定版本密钥加密",
   context_path= "/keys/{key_id}",
   params(
-    ("key_id" = String, Path, description="kms 标识"),
+    ("key_id" = String, Path, description="密钥标识"),
+    ("version" = String, Path, description="密钥版本"),
   ),
+  request_body = KeyEncryptBody,
   responses(
-      (status = 200, description = "密文信息", body = String, content_type="application/json"),
+      (status = 200, description = "密文信息", body = KeyEncryptResult, content_type="application/json"),
       (status = 400, description = "illegal params")
   ),
 )]
 pub async fn encrypt(
+    State(States { db, rd, .. }): State<States>,
+    Path((key_id, version)): Path<(String, String)>,
     Json(body): Json<KeyEncryptBody>,
 ) -> Result<impl IntoResponse> {
-    tracing::info!("encrypt data: body {:?}", body);
-    Ok("")
+    tracing::info!(
+        "encrypt data, key_id: {}, version: {}, body: {:?}",
+        key_id,
+        version,
+        body
+    );
+    crypto_service::encrypt(&rd, &db, &key_id, Some(&version), &body)
+        .await
+        .map(axum::Json)
 }
 
 #[utoipa::path(
@@ -32,32 +51,131 @@ pub async fn encrypt(
   operation_id = "增强加密，即仅用主密钥主版本",
   context_path= "/keys/{key_id}",
   params(
-    ("key_id" = String, Path, description="kms 标识"),
+    ("key_id" = String, Path, description="密钥标识"),
   ),
+  request_body = KeyEncryptBody,
   responses(
-      (status = 200, description = "密文信息", body = String, content_type="application/json"),
+      (status = 200, description = "密文信息", body = KeyEncryptResult, content_type="application/json"),
       (status = 400, description = "illegal params")
   ),
 )]
-pub async fn advance_encrypt() -> Result<impl IntoResponse> {
-    Ok("")
+pub async fn advance_encrypt(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyEncryptBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("encrypt data, key_id: {}, body: {:?}", key_id, body);
+    crypto_service::encrypt(&rd, &db, &key_id, None, &body)
+        .await
+        .map(axum::Json)
 }
 
 #[utoipa::path(
   post,
   path="/decrypt",
-  operation_id = "使用默认密钥解密",
+  operation_id = "解密，密钥版本与算法由密文决定",
+  context_path= "/keys/{key_id}",
+  params(
+    ("key_id" = String, Path, description="密钥标识"),
+  ),
+  request_body = KeyDecryptBody,
+  responses(
+      (status = 200, description = "明文信息", body = KeyDecryptResult, content_type="application/json"),
+      (status = 400, description = "illegal params")
+  ),
+)]
+pub async fn decrypt(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyDecryptBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("decrypt data, key_id: {}", key_id);
+    crypto_service::decrypt(&rd, &db, &key_id, &body)
+        .await
+        .map(axum::Json)
+}
+
+#[utoipa::path(
+  post,
+  path="/reencrypt",
+  operation_id = "转加密，使用目标密钥主版本重新加密密文",
+  context_path= "/keys/{key_id}",
+  params(
+    ("key_id" = String, Path, description="密文所属的密钥标识"),
+  ),
+  request_body = KeyReEncryptBody,
+  responses(
+      (status = 200, description = "新密文信息", body = KeyReEncryptResult, content_type="application/json"),
+      (status = 400, description = "illegal params")
+  ),
+)]
+pub async fn re_encrypt(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyReEncryptBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!(
+        "re-encrypt data, key_id: {}, destination_key_id: {:?}",
+        key_id,
+        body.destination_key_id
+    );
+    crypto_service::re_encrypt(&rd, &db, &key_id, &body)
+        .await
+        .map(axum::Json)
+}
+
+#[utoipa::path(
+  post,
+  path="/datakey",
+  operation_id = "生成数据密钥，返回明文与主密钥主版本加密的密文",
   context_path= "/keys/{key_id}",
   params(
-    ("key_id" = String, Path, description="kms 标识"),
+    ("key_id" = String, Path, description="密钥标识"),
   ),
+  request_body = KeyGenerateDataKeyBody,
   responses(
-      (status = 200, description = "密文信息", body = String, content_type="application/json"),
+      (status = 200, description = "数据密钥", body = KeyDataKeyResult, content_type="application/json"),
       (status = 400, description = "illegal params")
   ),
 )]
-pub async fn decrypt() -> Result<impl IntoResponse> {
-    Ok("")
+pub async fn generate_data_key(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyGenerateDataKeyBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("generate data key, key_id: {}, body: {:?}", key_id, body);
+    crypto_service::generate_data_key(&rd, &db, &key_id, &body, true)
+        .await
+        .map(axum::Json)
+}
+
+#[utoipa::path(
+  post,
+  path="/datakey/without-plaintext",
+  operation_id = "生成数据密钥，仅返回主密钥主版本加密的密文",
+  context_path= "/keys/{key_id}",
+  params(
+    ("key_id" = String, Path, description="密钥标识"),
+  ),
+  request_body = KeyGenerateDataKeyBody,
+  responses(
+      (status = 200, description = "数据密钥密文", body = KeyDataKeyResult, content_type="application/json"),
+      (status = 400, description = "illegal params")
+  ),
+)]
+pub async fn generate_data_key_without_plaintext(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyGenerateDataKeyBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!(
+        "generate data key without plaintext, key_id: {}, body: {:?}",
+        key_id,
+        body
+    );
+    crypto_service::generate_data_key(&rd, &db, &key_id, &body, false)
+        .await
+        .map(axum::Json)
 }
 
 #[utoipa::path(
@@ -66,15 +184,23 @@ pub async fn decrypt() -> Result<impl IntoResponse> {
   operation_id = "增强签名，用主密钥的主版本",
   context_path= "/keys/{key_id}",
   params(
-    ("key_id" = String, Path, description="kms 标识"),
+    ("key_id" = String, Path, description="密钥标识"),
   ),
+  request_body = KeySignBody,
   responses(
-      (status = 200, description = "密文信息", body = String, content_type="application/json"),
+      (status = 200, description = "签名信息", body = KeySignResult, content_type="application/json"),
       (status = 400, description = "illegal params")
   ),
 )]
-pub async fn advance_sign() -> Result<impl IntoResponse> {
-    Ok("")
+pub async fn advance_sign(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeySignBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("sign data, key_id: {}, body: {:?}", key_id, body);
+    crypto_service::sign(&rd, &db, &key_id, None, &body)
+        .await
+        .map(axum::Json)
 }
 
 #[utoipa::path(
@@ -83,15 +209,29 @@ pub async fn advance_sign() -> Result<impl IntoResponse> {
   operation_id = "签名",
   context_path= "/keys/{key_id}",
   params(
-    ("key_id" = String, Path, description="kms 标识"),
+    ("key_id" = String, Path, description="密钥标识"),
+    ("version" = String, Path, description="密钥版本"),
   ),
+  request_body = KeySignBody,
   responses(
-      (status = 200, description = "密文信息", body = String, content_type="application/json"),
+      (status = 200, description = "签名信息", body = KeySignResult, content_type="application/json"),
       (status = 400, description = "illegal params")
   ),
 )]
-pub async fn sign() -> Result<impl IntoResponse> {
-    Ok("")
+pub async fn sign(
+    State(States { db, rd, .. }): State<States>,
+    Path((key_id, version)): Path<(String, String)>,
+    Json(body): Json<KeySignBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!(
+        "sign data, key_id: {}, version: {}, body: {:?}",
+        key_id,
+        version,
+        body
+    );
+    crypto_service::sign(&rd, &db, &key_id, Some(&version), &body)
+        .await
+        .map(axum::Json)
 }
 
 #[utoipa::path(
@@ -100,13 +240,125 @@ pub async fn sign() -> Result<impl IntoResponse> {
   operation_id = "验签",
   context_path= "/keys/{key_id}",
   params(
-    ("key_id" = String, Path, description="kms 标识"),
+    ("key_id" = String, Path, description="密钥标识"),
+  ),
+  request_body = KeyVerifyBody,
+  responses(
+      (status = 200, description = "验签结果", body = KeyVerifyResult, content_type="application/json"),
+      (status = 400, description = "illegal params")
+  ),
+)]
+pub async fn verify(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyVerifyBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("verify data, key_id: {}, body: {:?}", key_id, body);
+    crypto_service::verify(&rd, &db, &key_id, &body)
+        .await
+        .map(axum::Json)
+}
+
+#[utoipa::path(
+  post,
+  path="/mac",
+  operation_id = "生成消息认证码",
+  context_path= "/keys/{key_id}",
+  params(
+    ("key_id" = String, Path, description="密钥标识"),
+  ),
+  request_body = KeyGenerateMacBody,
+  responses(
+      (status = 200, description = "消息认证码", body = KeyGenerateMacResult, content_type="application/json"),
+      (status = 400, description = "illegal params")
+  ),
+)]
+pub async fn generate_mac(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyGenerateMacBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("generate mac, key_id: {}, body: {:?}", key_id, body);
+    crypto_service::generate_mac(&rd, &db, &key_id, &body)
+        .await
+        .map(axum::Json)
+}
+
+#[utoipa::path(
+  post,
+  path="/mac/verify",
+  operation_id = "校验消息认证码",
+  context_path= "/keys/{key_id}",
+  params(
+    ("key_id" = String, Path, description="密钥标识"),
+  ),
+  request_body = KeyVerifyMacBody,
+  responses(
+      (status = 200, description = "校验结果", body = KeyVerifyMacResult, content_type="application/json"),
+      (status = 400, description = "illegal params")
+  ),
+)]
+pub async fn verify_mac(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyVerifyMacBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("verify mac, key_id: {}, body: {:?}", key_id, body);
+    crypto_service::verify_mac(&rd, &db, &key_id, &body)
+        .await
+        .map(axum::Json)
+}
+
+#[utoipa::path(
+  post,
+  path="/derive-shared-secret",
+  operation_id = "密钥协商，使用对端公钥计算共享秘密",
+  context_path= "/keys/{key_id}",
+  params(
+    ("key_id" = String, Path, description="密钥标识"),
   ),
+  request_body = KeyDeriveSharedSecretBody,
   responses(
-      (status = 200, description = "密文信息", body = String, content_type="application/json"),
+      (status = 200, description = "共享秘密或其密文", body = KeySharedSecretResult, content_type="application/json"),
       (status = 400, description = "illegal params")
   ),
 )]
-pub async fn verify() -> Result<impl IntoResponse> {
-    Ok("")
+pub async fn derive_shared_secret(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyDeriveSharedSecretBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!(
+        "derive shared secret, key_id: {}, body: {:?}",
+        key_id,
+        body
+    );
+    crypto_service::derive_shared_secret(&rd, &db, &key_id, &body)
+        .await
+        .map(axum::Json)
+}
+
+#[utoipa::path(
+  post,
+  path="/jws",
+  operation_id = "使用主密钥主版本签发 JWS",
+  context_path= "/keys/{key_id}",
+  params(
+    ("key_id" = String, Path, description="密钥标识"),
+  ),
+  request_body = KeyJwsBody,
+  responses(
+      (status = 200, description = "JWS 紧凑序列化", body = KeyJwsResult, content_type="application/json"),
+      (status = 400, description = "illegal params")
+  ),
+)]
+pub async fn sign_jws(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyJwsBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("sign jws, key_id: {}, body: {:?}", key_id, body);
+    crypto_service::sign_jws(&rd, &db, &key_id, &body)
+        .await
+        .map(axum::Json)
 }
@@ -9,8 +9,10 @@ use crate::{
         axum::{Json, Query},
         errors::{Result, ServiceError},
     },
-    crypto::algorithm,
-    pojo::form::key::{KeyCreateBody, KeyImportBody, KeyImportParamsQuery},
+    crypto::{algorithm, types::KeyOrigin},
+    pojo::form::key::{
+        KeyCreateBody, KeyImportBody, KeyImportParamsQuery, KeyPublicKeyQuery,
+    },
     service::key_service,
     States,
 };
@@ -40,7 +42,9 @@ pub async fn create_key(
             body.usage
         )));
     }
-    if !algorithm::SUPPORTED_EXTERNAL_SPEC.contains(&body.spec) {
+    if KeyOrigin::External.eq(&body.origin)
+        && !algorithm::SUPPORTED_EXTERNAL_SPEC.contains(&body.spec)
+    {
         return Err(ServiceError::Unsupported(format!(
             "external marterial spec is not supported: {:?}",
             body.spec,
@@ -118,3 +122,50 @@ pub async fn create_key_version(
         .await
         .map(axum::Json)
 }
+
+#[utoipa::path(
+    delete,
+    path="/material",
+    operation_id = "删除已导入的密钥材料",
+    context_path= "/keys/{key_id}",
+    params(
+        ("key_id" = String, Path, description="密钥标识"),
+    ),
+    responses(
+        (status = 200, description = "", body = ()),
+        (status = 409, description = "key material is not imported"),
+        (status = 418, description = "key origin is not external")
+    ),
+)]
+pub async fn delete_key_material(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("delete key material, key_id: {}", key_id);
+    key_service::delete_key_material(&rd, &db, &key_id).await
+}
+
+#[utoipa::path(
+    get,
+    path="/public-key",
+    operation_id = "获取公钥",
+    context_path= "/keys/{key_id}",
+    params(
+        ("key_id" = String, Path, description="密钥标识"),
+        KeyPublicKeyQuery,
+    ),
+    responses(
+        (status = 200, description = "公钥与可用算法", body = KeyPublicKeyResult),
+        (status = 400, description = "illegal params")
+    ),
+)]
+pub async fn get_public_key(
+    State(States { db, rd, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Query(query): Query<KeyPublicKeyQuery>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("get public key, key_id: {}, query: {:?}", key_id, query);
+    key_service::get_public_key(&rd, &db, &key_id, &query)
+        .await
+        .map(axum::Json)
+}
@@ -4,9 +4,20 @@ use axum::{
 };
 
 use crate::{
-    common::{axum::Json, configs::Patch, errors::Result},
-    pojo::form::key_extra::{KeyChangeStateBody, KeyMetaPatchForm},
-    service::key_meta_service::{self},
+    common::{
+        axum::{Json, Query},
+        configs::Patch,
+        datasource::Paginator,
+        errors::Result,
+    },
+    pojo::form::key_extra::{
+        KeyChangeStateBody, KeyMetaPatchForm, KeyScheduleDeletionBody,
+    },
+    service::{
+        key_history_service,
+        key_meta_service::{self},
+        key_service,
+    },
     States,
 };
 
@@ -86,6 +97,66 @@ pub async fn change_key_state(
         .map(axum::Json)
 }
 
+#[utoipa::path(
+    post,
+    path="/deletion",
+    operation_id = "计划删除密钥",
+    context_path= "/keys/{key_id}",
+    params(
+        ("key_id" = String, Path, description="密钥标识"),
+    ),
+    responses(
+        (status = 200, description = "密钥删除计划", body = KeyDeletionResult, content_type="application/json"),
+        (status = 400, description = "illegal params"),
+        (status = 409, description = "key is pending deletion")
+    ),
+    request_body = KeyScheduleDeletionBody
+)]
+pub async fn schedule_key_deletion(
+    State(States { db, rd, extra }): State<States>,
+    Path(key_id): Path<String>,
+    Json(body): Json<KeyScheduleDeletionBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!(
+        "schedule key deletion, key_id: {}, body: {:?}",
+        key_id,
+        body
+    );
+    key_service::schedule_key_deletion(
+        &rd,
+        &db,
+        &extra.re,
+        &extra.de,
+        &key_id,
+        body.pending_window_in_days.unwrap_or(30),
+    )
+    .await
+    .map(axum::Json)
+}
+
+#[utoipa::path(
+    post,
+    path="/deletion/cancel",
+    operation_id = "取消删除密钥，密钥恢复为禁用状态",
+    context_path= "/keys/{key_id}",
+    params(
+        ("key_id" = String, Path, description="密钥标识"),
+    ),
+    responses(
+        (status = 200, description = "密钥删除计划", body = KeyDeletionResult, content_type="application/json"),
+        (status = 409, description = "key is not pending deletion")
+    ),
+)]
+pub async fn cancel_key_deletion(
+    State(States { db, rd, extra }): State<States>,
+    Path(key_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("cancel key deletion, key_id: {}", key_id);
+    key_service::cancel_key_deletion(&rd, &db, &extra.re, &extra.de, &key_id)
+        .await
+        .map(axum::Json)
+}
+
 #[utoipa::path(
     get,
     path="",
@@ -132,3 +203,28 @@ pub async fn list_key_version(
         .await
         .map(axum::Json)
 }
+
+#[utoipa::path(
+    get,
+    path="/histories",
+    operation_id = "密钥操作历史的分页查询",
+    context_path= "/keys/{key_id}",
+    params(
+        ("key_id" = String, Path, description="密钥标识"),
+        Paginator
+    ),
+    responses(
+        (status = 200, description = "", body = PaginatedKeyHistoryModels),
+        (status = 400, description = "illegal params")
+    ),
+  )]
+pub async fn list_key_history(
+    State(States { db, .. }): State<States>,
+    Path(key_id): Path<String>,
+    Query(paginator): Query<Paginator>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("paging key history, key_id: {}, {:?}", key_id, paginator);
+    key_history_service::list_key_histories(&db, &key_id, paginator)
+        .await
+        .map(axum::Json)
+}
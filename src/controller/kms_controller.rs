@@ -11,7 +11,7 @@ use crate::{
     },
     pojo::form::kms::{KmsCreateBody, KmsPatchForm},
     repository::kms_repository,
-    service::kms_service,
+    service::{crypto_service, kms_service},
     States,
 };
 
@@ -112,3 +112,26 @@ pub async fn destroy_kms(
 
     Ok(().into_response())
 }
+
+#[utoipa::path(
+  get,
+  path="/.well-known/jwks.json",
+  operation_id = "kms 实例签名公钥集合",
+  context_path= "/kms/{kms_id}",
+  params(
+    ("kms_id" = String, Path, description="kms 标识"),
+  ),
+  responses(
+      (status = 200, description = "JWK Set", body = JwksResult, content_type="application/json"),
+      (status = 404, description = "kms is nonexistent")
+  ),
+)]
+pub async fn get_jwks(
+    State(States { db, rd, .. }): State<States>,
+    Path(kms_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("get jwks, kms_id: {}", kms_id);
+    crypto_service::get_jwks(&rd, &db, &kms_id)
+        .await
+        .map(axum::Json)
+}
@@ -0,0 +1,73 @@
+use axum::response::IntoResponse;
+
+use crate::{
+    common::{axum::Json, errors::Result},
+    pojo::form::sys::{SysInitBody, SysUnsealBody},
+    service::sys_service,
+};
+
+#[utoipa::path(
+  post,
+  path="/init",
+  operation_id = "初始化根密钥并拆分为分片",
+  context_path= "/sys",
+  request_body = SysInitBody,
+  responses(
+      (status = 200, description = "根密钥分片", body = SysInitResult, content_type="application/json"),
+      (status = 400, description = "illegal params"),
+      (status = 401, description = "operator token is invalid"),
+      (status = 418, description = "root key provider is not shamir")
+  ),
+)]
+pub async fn init(Json(body): Json<SysInitBody>) -> Result<impl IntoResponse> {
+    tracing::info!("init root key, body: {:?}", body);
+    sys_service::init(&body).await.map(axum::Json)
+}
+
+#[utoipa::path(
+  post,
+  path="/unseal",
+  operation_id = "提交分片解封根密钥",
+  context_path= "/sys",
+  request_body = SysUnsealBody,
+  responses(
+      (status = 200, description = "解封进度", body = SysStatusResult, content_type="application/json"),
+      (status = 400, description = "illegal params"),
+      (status = 418, description = "root key provider is not shamir")
+  ),
+)]
+pub async fn unseal(
+    Json(body): Json<SysUnsealBody>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("unseal root key");
+    sys_service::unseal(&body).await.map(axum::Json)
+}
+
+#[utoipa::path(
+  post,
+  path="/seal",
+  operation_id = "封存根密钥",
+  context_path= "/sys",
+  responses(
+      (status = 200, description = "封存状态", body = SysStatusResult, content_type="application/json"),
+      (status = 401, description = "operator token is invalid"),
+      (status = 418, description = "root key provider is not shamir")
+  ),
+)]
+pub async fn seal() -> Result<impl IntoResponse> {
+    tracing::info!("seal root key");
+    sys_service::seal().await.map(axum::Json)
+}
+
+#[utoipa::path(
+  get,
+  path="/status",
+  operation_id = "查询根密钥封存状态",
+  context_path= "/sys",
+  responses(
+      (status = 200, description = "封存状态", body = SysStatusResult, content_type="application/json"),
+  ),
+)]
+pub async fn status() -> Result<impl IntoResponse> {
+    sys_service::status().await.map(axum::Json)
+}
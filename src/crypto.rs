@@ -1,5 +1,16 @@
 pub mod algorithm;
+pub mod blob;
 pub mod ec;
+pub mod ecdh;
+pub mod eddsa;
+pub mod hmac;
+pub mod jws;
+pub mod pkcs11;
+pub mod public_key;
+pub mod root_key;
 pub mod rsa;
+pub mod shamir;
+pub mod sm2;
 pub mod symm;
 pub mod types;
+pub mod x509;
@@ -1,13 +1,19 @@
 use anyhow::Context;
-use openssl::{ec, hash, nid::Nid, pkey, rsa, symm};
+use openssl::{ec, hash, nid::Nid, pkey, pkey_ctx, rsa, symm};
 
 use super::{
-    ec::EcAlgorithmFactory,
-    rsa::RsaAlgorithmFactory,
-    symm::{AEADAlgorithmFactory, CipherAlgorithmFactory},
+    ec::{EcAlgorithmFactory, EciesAlgorithmFactory},
+    eddsa::EdDsaAlgorithmFactory,
+    hmac::HmacAlgorithmFactory,
+    rsa::{RsaAesKeyWrapFactory, RsaAlgorithmFactory},
+    sm2::Sm2AlgorithmFactory,
+    symm::{
+        generate_iv, AEADAlgorithmFactory, CipherAlgorithmFactory,
+        SivAlgorithmFactory,
+    },
     types::{
-        KeyAlgorithm, KeySpec, KeyType, KeyUsage, WrappingKeyAlgorithm,
-        WrappingKeySpec,
+        KeyAlgorithm, KeySpec, KeyType, KeyUsage, MessageDigest, MessageType,
+        WrappingKeyAlgorithm, WrappingKeySpec,
     },
 };
 use crate::common::{
@@ -15,8 +21,28 @@ use crate::common::{
     utils,
 };
 
-pub const SUPPORTED_EXTERNAL_SPEC: &[KeySpec] =
-    &[KeySpec::Aes128, KeySpec::Aes256];
+pub const SUPPORTED_EXTERNAL_SPEC: &[KeySpec] = &[
+    KeySpec::Aes128,
+    KeySpec::Aes256,
+    KeySpec::Rsa2048,
+    KeySpec::Rsa3072,
+    KeySpec::Rsa4096,
+    KeySpec::EcP256,
+    KeySpec::EcP256K,
+    KeySpec::EcP384,
+    KeySpec::EcP521,
+    KeySpec::HmacSha256,
+    KeySpec::HmacSha384,
+    KeySpec::HmacSha512,
+    KeySpec::HmacSm3,
+];
+
+pub const AEAD_TAG_SIZE: usize = 16;
+pub const XCHACHA20_NONCE_SIZE: usize = 24;
+pub const SIV_NONCE_SIZE: usize = 16;
+pub const GCM_SIV_NONCE_SIZE: usize = 12;
+// rfc 8998
+pub const SM4_AEAD_NONCE_SIZE: usize = 12;
 
 pub trait KeyAlgorithmFactory {
     fn sign(
@@ -51,6 +77,8 @@ pub struct CryptoAdaptor {
     pub padding: Option<openssl::rsa::Padding>,
     pub kits: Option<EncryptKits>,
     pub md: Option<openssl::hash::MessageDigest>,
+    // the plaintext of sign/verify is already digested by md
+    pub prehashed: bool,
 }
 #[derive(Default, Clone)]
 
@@ -64,37 +92,60 @@ pub struct KeyAlgorithmMeta {
     pub key_type: KeyType,
     pub key_size: usize,
     pub key_usage: Vec<KeyUsage>,
+    pub key_algorithms: Vec<KeyAlgorithm>,
+    pub digest: Option<MessageDigest>,
 }
 
 pub fn generate_key(spec: KeySpec) -> Result<(Vec<u8>, Vec<u8>)> {
     let (nid, size) = spec.into();
     match spec {
-        KeySpec::Aes128 | KeySpec::Aes256 | KeySpec::SM4 => symm_generate(size),
-        KeySpec::Rsa2048 | KeySpec::Rsa3072 => rsa_generate(size),
-        KeySpec::EcP256 | KeySpec::EcP256K => ec_generate(nid),
+        KeySpec::Aes128
+        | KeySpec::Aes256
+        | KeySpec::SM4
+        | KeySpec::HmacSha256
+        | KeySpec::HmacSha384
+        | KeySpec::HmacSha512
+        | KeySpec::HmacSm3 => symm_generate(size),
+        KeySpec::Rsa2048 | KeySpec::Rsa3072 | KeySpec::Rsa4096 => {
+            rsa_generate(size)
+        }
+        KeySpec::EcP256
+        | KeySpec::EcP256K
+        | KeySpec::EcP384
+        | KeySpec::EcP521 => ec_generate(nid),
+        KeySpec::EcSm2 => sm2_generate(),
+        KeySpec::Ed25519 | KeySpec::Ed448 => ed_generate(spec),
+        KeySpec::X25519 | KeySpec::X448 => x_generate(spec),
     }
 }
 
 pub fn generate_wrapping_key(
     spec: WrappingKeySpec,
 ) -> Result<(Vec<u8>, Vec<u8>)> {
-    let (nid, size) = spec.into();
+    let (_nid, size) = spec.into();
     match spec {
-        WrappingKeySpec::Rsa2048 => rsa_generate(size),
-        WrappingKeySpec::EcSm2 => ec_generate(nid),
+        WrappingKeySpec::Rsa2048 | WrappingKeySpec::Rsa4096 => {
+            rsa_generate(size)
+        }
+        WrappingKeySpec::EcSm2 => sm2_generate(),
     }
 }
 
 pub fn select_wrapping_meta(spec: WrappingKeySpec) -> KeyAlgorithmMeta {
+    let (_nid, size) = spec.into();
     match spec {
-        WrappingKeySpec::Rsa2048 => KeyAlgorithmMeta {
-            key_type: KeyType::Asymmetric,
-            key_size: 256,
-            key_usage: vec![
-                KeyUsage::EncryptAndDecrypt,
-                KeyUsage::SignAndVerify,
-            ],
-        },
+        WrappingKeySpec::Rsa2048 | WrappingKeySpec::Rsa4096 => {
+            KeyAlgorithmMeta {
+                key_type: KeyType::Asymmetric,
+                key_size: size,
+                key_usage: vec![
+                    KeyUsage::EncryptAndDecrypt,
+                    KeyUsage::SignAndVerify,
+                ],
+                key_algorithms: vec![KeyAlgorithm::RsaOAEP],
+                digest: Some(MessageDigest::Sha256),
+            }
+        }
         WrappingKeySpec::EcSm2 => KeyAlgorithmMeta {
             key_type: KeyType::Asymmetric,
             key_size: 256,
@@ -102,6 +153,8 @@ pub fn select_wrapping_meta(spec: WrappingKeySpec) -> KeyAlgorithmMeta {
                 KeyUsage::SignAndVerify,
                 KeyUsage::EncryptAndDecrypt,
             ],
+            key_algorithms: vec![KeyAlgorithm::SM2PKE],
+            digest: Some(MessageDigest::Sm3),
         },
     }
 }
@@ -109,41 +162,162 @@ pub fn select_wrapping_meta(spec: WrappingKeySpec) -> KeyAlgorithmMeta {
 pub fn select_algorithm_meta(spec: KeySpec) -> KeyAlgorithmMeta {
     let (_nid, size) = spec.into();
     match spec {
-        KeySpec::Aes128 | KeySpec::SM4 => KeyAlgorithmMeta {
+        KeySpec::Aes128 => KeyAlgorithmMeta {
+            key_type: KeyType::Symmetric,
+            key_size: size,
+            key_usage: vec![KeyUsage::EncryptAndDecrypt],
+            key_algorithms: vec![
+                KeyAlgorithm::AesGCM,
+                KeyAlgorithm::AesCBC,
+                KeyAlgorithm::AesGcmSIV,
+            ],
+            digest: None,
+        },
+        KeySpec::SM4 => KeyAlgorithmMeta {
             key_type: KeyType::Symmetric,
             key_size: size,
             key_usage: vec![KeyUsage::EncryptAndDecrypt],
+            key_algorithms: vec![
+                KeyAlgorithm::Sm4CBC,
+                KeyAlgorithm::Sm4CTR,
+                KeyAlgorithm::Sm4GCM,
+                KeyAlgorithm::Sm4CCM,
+            ],
+            digest: None,
         },
 
         KeySpec::Aes256 => KeyAlgorithmMeta {
             key_type: KeyType::Symmetric,
             key_size: size,
             key_usage: vec![KeyUsage::EncryptAndDecrypt],
+            key_algorithms: vec![
+                KeyAlgorithm::AesGCM,
+                KeyAlgorithm::AesCBC,
+                KeyAlgorithm::ChaCha20Poly1305,
+                KeyAlgorithm::XChaCha20Poly1305,
+                KeyAlgorithm::AesSIV,
+                KeyAlgorithm::AesGcmSIV,
+            ],
+            digest: None,
         },
-        KeySpec::Rsa2048 | KeySpec::Rsa3072 => KeyAlgorithmMeta {
+        KeySpec::Rsa2048 | KeySpec::Rsa3072 | KeySpec::Rsa4096 => {
+            KeyAlgorithmMeta {
+                key_type: KeyType::Asymmetric,
+                key_size: size,
+                key_usage: vec![
+                    KeyUsage::EncryptAndDecrypt,
+                    KeyUsage::SignAndVerify,
+                ],
+                key_algorithms: vec![
+                    KeyAlgorithm::RsaOAEP,
+                    KeyAlgorithm::RsaPSS,
+                    KeyAlgorithm::RsaPKCS1,
+                ],
+                digest: Some(MessageDigest::Sha256),
+            }
+        }
+
+        KeySpec::EcP256 | KeySpec::EcP384 => KeyAlgorithmMeta {
             key_type: KeyType::Asymmetric,
             key_size: size,
             key_usage: vec![
                 KeyUsage::EncryptAndDecrypt,
                 KeyUsage::SignAndVerify,
+                KeyUsage::KeyAgreement,
+            ],
+            key_algorithms: vec![
+                KeyAlgorithm::Ecdsa,
+                KeyAlgorithm::EciesHkdfAesGCM,
+                KeyAlgorithm::Ecdh,
             ],
+            digest: Some(match spec {
+                KeySpec::EcP384 => MessageDigest::Sha384,
+                _ => MessageDigest::Sha256,
+            }),
         },
-
-        KeySpec::EcP256 | KeySpec::EcP256K => KeyAlgorithmMeta {
+        KeySpec::EcP256K | KeySpec::EcP521 => KeyAlgorithmMeta {
+            key_type: KeyType::Asymmetric,
+            key_size: size,
+            key_usage: vec![KeyUsage::SignAndVerify, KeyUsage::KeyAgreement],
+            key_algorithms: vec![KeyAlgorithm::Ecdsa, KeyAlgorithm::Ecdh],
+            digest: Some(match spec {
+                KeySpec::EcP521 => MessageDigest::Sha512,
+                _ => MessageDigest::Sha256,
+            }),
+        },
+        KeySpec::EcSm2 => KeyAlgorithmMeta {
+            key_type: KeyType::Asymmetric,
+            key_size: size,
+            key_usage: vec![
+                KeyUsage::EncryptAndDecrypt,
+                KeyUsage::SignAndVerify,
+            ],
+            key_algorithms: vec![KeyAlgorithm::SM2PKE, KeyAlgorithm::SM2DSA],
+            digest: Some(MessageDigest::Sm3),
+        },
+        KeySpec::Ed25519 | KeySpec::Ed448 => KeyAlgorithmMeta {
             key_type: KeyType::Asymmetric,
             key_size: size,
             key_usage: vec![KeyUsage::SignAndVerify],
+            key_algorithms: vec![KeyAlgorithm::EdDSA],
+            digest: None,
+        },
+        KeySpec::X25519 | KeySpec::X448 => KeyAlgorithmMeta {
+            key_type: KeyType::Asymmetric,
+            key_size: size,
+            key_usage: vec![KeyUsage::KeyAgreement],
+            key_algorithms: vec![KeyAlgorithm::Ecdh],
+            digest: None,
+        },
+        KeySpec::HmacSha256
+        | KeySpec::HmacSha384
+        | KeySpec::HmacSha512
+        | KeySpec::HmacSm3 => KeyAlgorithmMeta {
+            key_type: KeyType::Symmetric,
+            key_size: size,
+            key_usage: vec![KeyUsage::GenerateAndVerifyMac],
+            key_algorithms: vec![KeyAlgorithm::Hmac],
+            digest: Some(match spec {
+                KeySpec::HmacSha384 => MessageDigest::Sha384,
+                KeySpec::HmacSha512 => MessageDigest::Sha512,
+                KeySpec::HmacSm3 => MessageDigest::Sm3,
+                _ => MessageDigest::Sha256,
+            }),
         },
     }
 }
 
 pub fn select_wrapping_factory(
     spec: WrappingKeySpec,
-) -> Box<dyn KeyAlgorithmFactory + Send> {
-    match spec {
-        WrappingKeySpec::Rsa2048 => Box::new(RsaAlgorithmFactory {}),
-        WrappingKeySpec::EcSm2 => Box::new(EcAlgorithmFactory {}),
-    }
+    alg: WrappingKeyAlgorithm,
+) -> Result<Box<dyn KeyAlgorithmFactory + Send>> {
+    Ok(match (spec, alg) {
+        (
+            WrappingKeySpec::Rsa2048 | WrappingKeySpec::Rsa4096,
+            WrappingKeyAlgorithm::RsaAesKeyWrapSha256,
+        ) => Box::new(RsaAesKeyWrapFactory {}),
+        (
+            WrappingKeySpec::Rsa2048 | WrappingKeySpec::Rsa4096,
+            WrappingKeyAlgorithm::SM2PKE,
+        ) => {
+            return Err(ServiceError::BadRequest(format!(
+                "wrapping algorithm {:?} is unmatched with {:?}",
+                alg, spec
+            )))
+        }
+        (WrappingKeySpec::Rsa2048 | WrappingKeySpec::Rsa4096, _) => {
+            Box::new(RsaAlgorithmFactory {})
+        }
+        (WrappingKeySpec::EcSm2, WrappingKeyAlgorithm::SM2PKE) => {
+            Box::new(Sm2AlgorithmFactory {})
+        }
+        (WrappingKeySpec::EcSm2, _) => {
+            return Err(ServiceError::BadRequest(format!(
+                "wrapping algorithm {:?} is unmatched with {:?}",
+                alg, spec
+            )))
+        }
+    })
 }
 
 pub fn select_cipher(
@@ -171,6 +345,12 @@ pub fn select_cipher(
                 )))
             }
         },
+        // xchacha20 runs chacha20-poly1305 under the hchacha20 subkey
+        KeyAlgorithm::ChaCha20Poly1305 | KeyAlgorithm::XChaCha20Poly1305
+            if size * 8 == 256 =>
+        {
+            symm::Cipher::chacha20_poly1305()
+        }
         KeyAlgorithm::Sm4CTR => symm::Cipher::sm4_ctr(),
         KeyAlgorithm::Sm4CBC => symm::Cipher::sm4_cbc(),
         _ => {
@@ -182,34 +362,197 @@ pub fn select_cipher(
     })
 }
 
+// aead ciphers without a static EVP_CIPHER, fetched from the provider by name
+pub fn select_fetched_cipher(
+    size: usize,
+    key_alg: KeyAlgorithm,
+) -> Result<&'static str> {
+    Ok(match key_alg {
+        KeyAlgorithm::AesGcmSIV => match size * 8 {
+            128 => "AES-128-GCM-SIV",
+            256 => "AES-256-GCM-SIV",
+            _ => {
+                return Err(ServiceError::Unsupported(format!(
+                    "unsupported aes gcm siv key length: {}",
+                    size
+                )))
+            }
+        },
+        KeyAlgorithm::Sm4GCM if size == 16 => "SM4-GCM",
+        KeyAlgorithm::Sm4CCM if size == 16 => "SM4-CCM",
+        _ => {
+            return Err(ServiceError::Unsupported(format!(
+                "unsupported {:?} key length: {}",
+                key_alg, size
+            )))
+        }
+    })
+}
+
 pub fn select_factory(
     alg: KeyAlgorithm,
 ) -> Result<Box<dyn KeyAlgorithmFactory>> {
     match alg {
-        KeyAlgorithm::AesCBC
-        | KeyAlgorithm::Sm4CBC
-        | KeyAlgorithm::SM2DSA
-        | KeyAlgorithm::SM2PKE => {
+        KeyAlgorithm::AesCBC | KeyAlgorithm::Sm4CBC | KeyAlgorithm::Sm4CTR => {
             Ok(Box::new(CipherAlgorithmFactory::new(alg)))
         }
-        KeyAlgorithm::AesGCM => Ok(Box::new(AEADAlgorithmFactory::new(alg))),
+        KeyAlgorithm::AesGCM
+        | KeyAlgorithm::ChaCha20Poly1305
+        | KeyAlgorithm::XChaCha20Poly1305
+        | KeyAlgorithm::Sm4GCM
+        | KeyAlgorithm::Sm4CCM => Ok(Box::new(AEADAlgorithmFactory::new(alg))),
+        KeyAlgorithm::AesSIV | KeyAlgorithm::AesGcmSIV => {
+            Ok(Box::new(SivAlgorithmFactory::new(alg)))
+        }
         KeyAlgorithm::RsaOAEP
         | KeyAlgorithm::RsaPSS
         | KeyAlgorithm::RsaPKCS1 => Ok(Box::new(RsaAlgorithmFactory {})),
         KeyAlgorithm::Ecdsa | KeyAlgorithm::EciesSha1 => {
             Ok(Box::new(EcAlgorithmFactory {}))
         }
-        _ => Err(ServiceError::Unsupported(format!(
-            "unsupported alg {:?}",
-            alg
-        ))),
+        KeyAlgorithm::EciesHkdfAesGCM => Ok(Box::new(EciesAlgorithmFactory {})),
+        KeyAlgorithm::SM2PKE | KeyAlgorithm::SM2DSA => {
+            Ok(Box::new(Sm2AlgorithmFactory {}))
+        }
+        KeyAlgorithm::EdDSA => Ok(Box::new(EdDsaAlgorithmFactory {})),
+        KeyAlgorithm::Hmac => Ok(Box::new(HmacAlgorithmFactory {})),
+        // key agreement is served by crypto::ecdh
+        KeyAlgorithm::Ecdh => Err(ServiceError::Unsupported(
+            "ecdh only derives shared secrets".to_owned(),
+        )),
     }
 }
+
+// fill the per-encryption parameters (iv, aad, tag buffer) of an algorithm,
+// deterministic output is only offered by the siv family: aes-siv drops the
+// nonce and aes-gcm-siv fixes it, equal plaintexts then only leak equality
+pub fn select_encrypt_adaptor(
+    key_alg: KeyAlgorithm,
+    key: &[u8],
+    aad: Vec<u8>,
+    deterministic: bool,
+) -> Result<CryptoAdaptor> {
+    let mut adaptor: CryptoAdaptor = key_alg.into();
+    match key_alg {
+        KeyAlgorithm::AesSIV | KeyAlgorithm::AesGcmSIV => {
+            let iv = match (key_alg, deterministic) {
+                (KeyAlgorithm::AesSIV, true) => vec![],
+                (KeyAlgorithm::AesSIV, false) => generate_iv(SIV_NONCE_SIZE)?,
+                (_, true) => vec![0; GCM_SIV_NONCE_SIZE],
+                (_, false) => generate_iv(GCM_SIV_NONCE_SIZE)?,
+            };
+            adaptor.kits = Some(EncryptKits {
+                iv,
+                aad,
+                tag: vec![0; AEAD_TAG_SIZE],
+            });
+            return Ok(adaptor);
+        }
+        // the nonce is derived and the tag is carried by the ciphertext
+        KeyAlgorithm::EciesHkdfAesGCM if !deterministic => {
+            adaptor.kits = Some(EncryptKits {
+                aad,
+                ..Default::default()
+            });
+        }
+        _ if deterministic => {
+            return Err(ServiceError::Unsupported(format!(
+                "deterministic encryption is unsupported by {:?}",
+                key_alg
+            )))
+        }
+        KeyAlgorithm::AesGCM
+        | KeyAlgorithm::ChaCha20Poly1305
+        | KeyAlgorithm::XChaCha20Poly1305
+        | KeyAlgorithm::Sm4GCM
+        | KeyAlgorithm::Sm4CCM => {
+            let iv_len = match key_alg {
+                KeyAlgorithm::XChaCha20Poly1305 => XCHACHA20_NONCE_SIZE,
+                KeyAlgorithm::Sm4GCM | KeyAlgorithm::Sm4CCM => {
+                    select_fetched_cipher(key.len(), key_alg)?;
+                    SM4_AEAD_NONCE_SIZE
+                }
+                _ => select_cipher(key.len(), key_alg)?
+                    .iv_len()
+                    .unwrap_or_default(),
+            };
+            adaptor.kits = Some(EncryptKits {
+                iv: generate_iv(iv_len)?,
+                aad,
+                tag: vec![0; AEAD_TAG_SIZE],
+            });
+        }
+        KeyAlgorithm::AesCBC | KeyAlgorithm::Sm4CBC | KeyAlgorithm::Sm4CTR => {
+            let cipher = select_cipher(key.len(), key_alg)?;
+            adaptor.kits = Some(EncryptKits {
+                iv: generate_iv(cipher.iv_len().unwrap_or_default())?,
+                ..Default::default()
+            });
+        }
+        _ => {}
+    }
+    Ok(adaptor)
+}
+
+// the digest of hmac is bound to the key spec
+pub fn select_mac_adaptor(spec: KeySpec) -> Result<CryptoAdaptor> {
+    let meta = select_algorithm_meta(spec);
+    if !meta.key_algorithms.contains(&KeyAlgorithm::Hmac) {
+        return Err(ServiceError::Unsupported(format!(
+            "mac is unsupported by key spec {:?}",
+            spec
+        )));
+    }
+    let mut adaptor: CryptoAdaptor = KeyAlgorithm::Hmac.into();
+    adaptor.md = meta.digest.map(Into::into);
+    Ok(adaptor)
+}
+
+// sm2 signature is always computed with sm3, others fall back to the default
+// digest of the key spec
+pub fn select_sign_adaptor(
+    spec: KeySpec,
+    key_alg: KeyAlgorithm,
+    digest: Option<MessageDigest>,
+    message_type: MessageType,
+) -> Result<CryptoAdaptor> {
+    let digest = match key_alg {
+        // pure eddsa hashes the raw message internally
+        KeyAlgorithm::EdDSA => {
+            if digest.is_some() || MessageType::Digest.eq(&message_type) {
+                return Err(ServiceError::Unsupported(format!(
+                    "{:?} only signs the raw message",
+                    key_alg
+                )));
+            }
+            return Ok(key_alg.into());
+        }
+        KeyAlgorithm::SM2DSA => match digest {
+            None | Some(MessageDigest::Sm3) => MessageDigest::Sm3,
+            Some(digest) => {
+                return Err(ServiceError::Unsupported(format!(
+                    "{:?} is unsupported by {:?}",
+                    digest, key_alg
+                )))
+            }
+        },
+        _ => digest.or(select_algorithm_meta(spec).digest).ok_or(
+            ServiceError::Unsupported(format!(
+                "message digest is required, spec: {:?}",
+                spec
+            )),
+        )?,
+    };
+    let mut adaptor: CryptoAdaptor = key_alg.into();
+    adaptor.md = Some(digest.into());
+    adaptor.prehashed = MessageType::Digest.eq(&message_type);
+    Ok(adaptor)
+}
+
 fn symm_generate(size: usize) -> Result<(Vec<u8>, Vec<u8>)> {
     Ok((utils::generate_key(size)?, vec![]))
 }
 
-// currently rust-openssl cant be generate oid = sm2ecc algorithm  pkcs8 key
 fn ec_generate(nid: Nid) -> Result<(Vec<u8>, Vec<u8>)> {
     let ec_group = ec::EcGroup::from_curve_name(nid)
         .context(format!("ec group create failed, curve_name: {:?}", nid))?;
@@ -225,6 +568,49 @@ fn ec_generate(nid: Nid) -> Result<(Vec<u8>, Vec<u8>)> {
     ))
 }
 
+// generated by the sm2 key type rather than a generic ec key, the pkcs8 and
+// spki carry the sm2p256v1 curve oid (1.2.156.10197.1.301) per GM/T 0010
+fn sm2_generate() -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut ctx = pkey_ctx::PkeyCtx::new_id(pkey::Id::SM2)
+        .context("sm2 pkey context create failed")?;
+    ctx.keygen_init().context("sm2 keygen init failed")?;
+    let pkey = ctx.keygen().context("generate sm2 key failed")?;
+    Ok((
+        pkey.private_key_to_pkcs8()
+            .context("export sm2 private key failed")?,
+        pkey.public_key_to_der()
+            .context("export sm2 public key failed")?,
+    ))
+}
+
+fn ed_generate(spec: KeySpec) -> Result<(Vec<u8>, Vec<u8>)> {
+    let pkey = match spec {
+        KeySpec::Ed448 => pkey::PKey::generate_ed448(),
+        _ => pkey::PKey::generate_ed25519(),
+    }
+    .context(format!("generate eddsa key failed, spec: {:?}", spec))?;
+    Ok((
+        pkey.private_key_to_pkcs8()
+            .context("export eddsa private key failed")?,
+        pkey.public_key_to_der()
+            .context("export eddsa public key failed")?,
+    ))
+}
+
+fn x_generate(spec: KeySpec) -> Result<(Vec<u8>, Vec<u8>)> {
+    let pkey = match spec {
+        KeySpec::X448 => pkey::PKey::generate_x448(),
+        _ => pkey::PKey::generate_x25519(),
+    }
+    .context(format!("generate x25519/x448 key failed, spec: {:?}", spec))?;
+    Ok((
+        pkey.private_key_to_pkcs8()
+            .context("export x25519/x448 private key failed")?,
+        pkey.public_key_to_der()
+            .context("export x25519/x448 public key failed")?,
+    ))
+}
+
 fn rsa_generate(size: usize) -> Result<(Vec<u8>, Vec<u8>)> {
     let rrg = rsa::Rsa::generate((size * 8) as u32)
         .context("rsa generate key failed")?;
@@ -237,11 +623,153 @@ fn rsa_generate(size: usize) -> Result<(Vec<u8>, Vec<u8>)> {
             .context("export rsa private key failed")?,
     ))
 }
+// validates imported material against the declared spec, asymmetric material
+// is a pkcs#8 der private key
+pub fn validate_key(spec: KeySpec, key: &[u8]) -> Result<()> {
+    let (nid, size) = spec.into();
+    match spec {
+        KeySpec::Aes128
+        | KeySpec::Aes256
+        | KeySpec::SM4
+        | KeySpec::HmacSha256
+        | KeySpec::HmacSha384
+        | KeySpec::HmacSha512
+        | KeySpec::HmacSm3 => {
+            if size != key.len() {
+                return Err(ServiceError::BadRequest(format!(
+                    "key length is invalid, expect: {}, actual: {}",
+                    size,
+                    key.len()
+                )));
+            }
+        }
+        KeySpec::Rsa2048 | KeySpec::Rsa3072 | KeySpec::Rsa4096 => {
+            let rsa = pkey::PKey::private_key_from_pkcs8(key)
+                .and_then(|pkey| pkey.rsa())
+                .map_err(|_| {
+                    ServiceError::BadRequest(
+                        "key material is not a pkcs8 rsa private key"
+                            .to_owned(),
+                    )
+                })?;
+            if size != rsa.size() as usize {
+                return Err(ServiceError::BadRequest(format!(
+                    "rsa modulus is invalid, expect: {} bits, actual: {} bits",
+                    size * 8,
+                    rsa.size() * 8
+                )));
+            }
+            if !rsa.check_key().unwrap_or(false) {
+                return Err(ServiceError::BadRequest(
+                    "rsa private key is inconsistent".to_owned(),
+                ));
+            }
+        }
+        KeySpec::EcP256
+        | KeySpec::EcP256K
+        | KeySpec::EcP384
+        | KeySpec::EcP521 => {
+            let ec_key = pkey::PKey::private_key_from_pkcs8(key)
+                .and_then(|pkey| pkey.ec_key())
+                .map_err(|_| {
+                    ServiceError::BadRequest(
+                        "key material is not a pkcs8 ec private key".to_owned(),
+                    )
+                })?;
+            let curve_name = ec_key.group().curve_name();
+            if Some(nid) != curve_name {
+                return Err(ServiceError::BadRequest(format!(
+                    "ec curve is invalid, expect: {:?}, actual: {:?}",
+                    nid, curve_name
+                )));
+            }
+            ec_key.check_key().map_err(|_| {
+                ServiceError::BadRequest(
+                    "ec private key is inconsistent".to_owned(),
+                )
+            })?;
+        }
+        KeySpec::Ed25519 | KeySpec::Ed448 => {
+            let pkey =
+                pkey::PKey::private_key_from_pkcs8(key).map_err(|_| {
+                    ServiceError::BadRequest(
+                        "key material is not a pkcs8 eddsa private key"
+                            .to_owned(),
+                    )
+                })?;
+            if nid.as_raw() != pkey.id().as_raw() {
+                return Err(ServiceError::BadRequest(format!(
+                    "eddsa curve is invalid, expect: {:?}",
+                    nid
+                )));
+            }
+        }
+        KeySpec::X25519 | KeySpec::X448 => {
+            let pkey =
+                pkey::PKey::private_key_from_pkcs8(key).map_err(|_| {
+                    ServiceError::BadRequest(
+                        "key material is not a pkcs8 x25519/x448 private key"
+                            .to_owned(),
+                    )
+                })?;
+            if nid.as_raw() != pkey.id().as_raw() {
+                return Err(ServiceError::BadRequest(format!(
+                    "montgomery curve is invalid, expect: {:?}",
+                    nid
+                )));
+            }
+        }
+        // openssl reports neither an ec key nor the sm2 id for sm2 keys
+        // decoded by the provider, the sec1 encoding of the key is checked
+        // on its group instead
+        KeySpec::EcSm2 => {
+            let pkey =
+                pkey::PKey::private_key_from_pkcs8(key).map_err(|_| {
+                    ServiceError::BadRequest(
+                        "key material is not a pkcs8 sm2 private key"
+                            .to_owned(),
+                    )
+                })?;
+            let ec_key = pkey
+                .private_key_to_der()
+                .and_then(|der| ec::EcKey::private_key_from_der(&der))
+                .context("export sm2 private key failed")?;
+            let curve_name = ec_key.group().curve_name();
+            if Some(nid) != curve_name {
+                return Err(ServiceError::BadRequest(format!(
+                    "ec curve is invalid, expect: {:?}, actual: {:?}",
+                    nid, curve_name
+                )));
+            }
+            ec_key.check_key().map_err(|_| {
+                ServiceError::BadRequest(
+                    "sm2 private key is inconsistent".to_owned(),
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
 pub fn derive_key(spec: KeySpec, key: &[u8]) -> Result<Vec<u8>> {
     match spec {
-        KeySpec::Aes128 | KeySpec::Aes256 | KeySpec::SM4 => Ok(vec![]),
-        KeySpec::Rsa2048 | KeySpec::Rsa3072 => rsa_derive(key),
-        KeySpec::EcP256 | KeySpec::EcP256K => ec_derive(key),
+        KeySpec::Aes128
+        | KeySpec::Aes256
+        | KeySpec::SM4
+        | KeySpec::HmacSha256
+        | KeySpec::HmacSha384
+        | KeySpec::HmacSha512
+        | KeySpec::HmacSm3 => Ok(vec![]),
+        KeySpec::Rsa2048 | KeySpec::Rsa3072 | KeySpec::Rsa4096 => {
+            rsa_derive(key)
+        }
+        KeySpec::EcP256
+        | KeySpec::EcP256K
+        | KeySpec::EcP384
+        | KeySpec::EcP521 => ec_derive(key),
+        KeySpec::EcSm2 => ec_derive(key),
+        KeySpec::Ed25519 | KeySpec::Ed448 => ed_derive(key),
+        KeySpec::X25519 | KeySpec::X448 => x_derive(key),
     }
 }
 
@@ -253,6 +781,22 @@ fn ec_derive(private_key: &[u8]) -> Result<Vec<u8>> {
         .context("export ec public key failed")?)
 }
 
+fn ed_derive(private_key: &[u8]) -> Result<Vec<u8>> {
+    let pkey = pkey::PKey::private_key_from_pkcs8(private_key)
+        .context("import eddsa private key failed")?;
+    Ok(pkey
+        .public_key_to_der()
+        .context("export eddsa public key failed")?)
+}
+
+fn x_derive(private_key: &[u8]) -> Result<Vec<u8>> {
+    let pkey = pkey::PKey::private_key_from_pkcs8(private_key)
+        .context("import x25519/x448 private key failed")?;
+    Ok(pkey
+        .public_key_to_der()
+        .context("export x25519/x448 public key failed")?)
+}
+
 fn rsa_derive(private_key: &[u8]) -> Result<Vec<u8>> {
     let pkey_pair = pkey::PKey::private_key_from_pkcs8(private_key)
         .context("import rsa key failed")?;
@@ -273,15 +817,81 @@ impl From<WrappingKeyAlgorithm> for CryptoAdaptor {
                 md: Some(hash::MessageDigest::sha1()),
                 ..Default::default()
             },
-            WrappingKeyAlgorithm::RsaesOaepSha256 => CryptoAdaptor {
+            WrappingKeyAlgorithm::RsaesOaepSha256
+            | WrappingKeyAlgorithm::RsaAesKeyWrapSha256 => CryptoAdaptor {
                 padding: Some(rsa::Padding::PKCS1_OAEP),
                 md: Some(hash::MessageDigest::sha256()),
                 ..Default::default()
             },
             WrappingKeyAlgorithm::SM2PKE => CryptoAdaptor {
+                md: Some(hash::MessageDigest::sm3()),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl From<KeyAlgorithm> for CryptoAdaptor {
+    fn from(value: KeyAlgorithm) -> Self {
+        match value {
+            KeyAlgorithm::RsaOAEP => CryptoAdaptor {
+                padding: Some(rsa::Padding::PKCS1_OAEP),
                 md: Some(hash::MessageDigest::sha256()),
                 ..Default::default()
             },
+            KeyAlgorithm::RsaPSS => CryptoAdaptor {
+                padding: Some(rsa::Padding::PKCS1_PSS),
+                ..Default::default()
+            },
+            KeyAlgorithm::RsaPKCS1 => CryptoAdaptor {
+                padding: Some(rsa::Padding::PKCS1),
+                ..Default::default()
+            },
+            _ => CryptoAdaptor::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use openssl::{ec, nid::Nid, pkey};
+
+    use super::{generate_key, validate_key};
+    use crate::crypto::types::KeySpec;
+
+    #[test]
+    fn test_validate_key() {
+        let (rsa_key, _) = generate_key(KeySpec::Rsa2048).unwrap();
+        let (ec_key, _) = generate_key(KeySpec::EcP256).unwrap();
+        let (aes_key, _) = generate_key(KeySpec::Aes256).unwrap();
+        let (sm2_key, _) = generate_key(KeySpec::EcSm2).unwrap();
+        assert!(validate_key(KeySpec::Rsa2048, &rsa_key).is_ok());
+        assert!(validate_key(KeySpec::Rsa3072, &rsa_key).is_err());
+        assert!(validate_key(KeySpec::EcP256, &ec_key).is_ok());
+        assert!(validate_key(KeySpec::EcP256K, &ec_key).is_err());
+        assert!(validate_key(KeySpec::EcP256, &rsa_key).is_err());
+        assert!(validate_key(KeySpec::Aes256, &aes_key).is_ok());
+        assert!(validate_key(KeySpec::Aes128, &aes_key).is_err());
+        assert!(validate_key(KeySpec::EcSm2, &sm2_key).is_ok());
+        assert!(validate_key(KeySpec::EcSm2, &ec_key).is_err());
+        assert!(validate_key(KeySpec::EcP256, &sm2_key).is_err());
+
+        // an sm2 private key paired with another public key
+        let group = ec::EcGroup::from_curve_name(Nid::SM2).unwrap();
+        let (left, right) = (
+            ec::EcKey::generate(&group).unwrap(),
+            ec::EcKey::generate(&group).unwrap(),
+        );
+        let mismatched = ec::EcKey::from_private_components(
+            &group,
+            left.private_key(),
+            right.public_key(),
+        )
+        .unwrap();
+        let mismatched = pkey::PKey::from_ec_key(mismatched)
+            .unwrap()
+            .private_key_to_pkcs8()
+            .unwrap();
+        assert!(validate_key(KeySpec::EcSm2, &mismatched).is_err());
+    }
+}
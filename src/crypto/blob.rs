@@ -0,0 +1,129 @@
+use super::types::KeyAlgorithm;
+use crate::common::{
+    errors::{Result, ServiceError},
+    utils,
+};
+
+pub const CIPHERTEXT_BLOB_VERSION: u8 = 1;
+
+// ciphertext blob layout, all length prefixes are one byte:
+// | blob version | algorithm | key_id len | key_id | key version len |
+// | key version | iv len | iv | tag len | tag | ciphertext ... |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiphertextBlob {
+    pub key_id: String,
+    pub version: String,
+    pub algorithm: KeyAlgorithm,
+    pub iv: Vec<u8>,
+    pub tag: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+impl CiphertextBlob {
+    pub fn encode(&self) -> Result<String> {
+        let mut blob = vec![CIPHERTEXT_BLOB_VERSION, self.algorithm as u8];
+        for field in [
+            self.key_id.as_bytes(),
+            self.version.as_bytes(),
+            &self.iv,
+            &self.tag,
+        ] {
+            let len = u8::try_from(field.len()).map_err(|_| {
+                ServiceError::BadRequest(format!(
+                    "ciphertext blob field is too long: {}",
+                    field.len()
+                ))
+            })?;
+            blob.push(len);
+            blob.extend_from_slice(field);
+        }
+        blob.extend_from_slice(&self.ciphertext);
+        Ok(utils::encode64(&blob))
+    }
+
+    pub fn decode(blob: &str) -> Result<Self> {
+        let blob = utils::decode64(blob)?;
+        let mut reader = BlobReader { blob: &blob };
+        let blob_version = reader.byte()?;
+        if CIPHERTEXT_BLOB_VERSION != blob_version {
+            return Err(ServiceError::BadRequest(format!(
+                "unsupported ciphertext blob version: {}",
+                blob_version
+            )));
+        }
+        let algorithm = KeyAlgorithm::try_from(reader.byte()?)?;
+        let key_id = reader.string()?;
+        let version = reader.string()?;
+        let iv = reader.bytes()?.to_vec();
+        let tag = reader.bytes()?.to_vec();
+        Ok(CiphertextBlob {
+            key_id,
+            version,
+            algorithm,
+            iv,
+            tag,
+            ciphertext: reader.blob.to_vec(),
+        })
+    }
+}
+
+struct BlobReader<'a> {
+    blob: &'a [u8],
+}
+
+impl<'a> BlobReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.blob.len() < len {
+            return Err(ServiceError::BadRequest(
+                "ciphertext blob is truncated".to_owned(),
+            ));
+        }
+        let (head, tail) = self.blob.split_at(len);
+        self.blob = tail;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.byte()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| {
+            ServiceError::BadRequest(
+                "ciphertext blob contains invalid utf-8".to_owned(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CiphertextBlob;
+    use crate::{common::utils, crypto::types::KeyAlgorithm};
+
+    #[test]
+    fn test_ciphertext_blob() {
+        let blob = CiphertextBlob {
+            key_id: utils::generate_b62(32).unwrap(),
+            version: utils::uuid(),
+            algorithm: KeyAlgorithm::AesGCM,
+            iv: utils::generate_key(12).unwrap(),
+            tag: utils::generate_key(16).unwrap(),
+            ciphertext: b"ciphertext".to_vec(),
+        };
+        let encoded = blob.encode().unwrap();
+        let decoded = CiphertextBlob::decode(&encoded).unwrap();
+        assert_eq!(decoded.key_id, blob.key_id);
+        assert_eq!(decoded.version, blob.version);
+        assert_eq!(decoded.algorithm, blob.algorithm);
+        assert_eq!(decoded.iv, blob.iv);
+        assert_eq!(decoded.tag, blob.tag);
+        assert_eq!(decoded.ciphertext, blob.ciphertext);
+        assert!(CiphertextBlob::decode(&encoded[.. 16]).is_err());
+    }
+}
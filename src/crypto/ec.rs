@@ -1,16 +1,67 @@
 use anyhow::Context;
 use openssl::{
+    bn, ec,
     encrypt::{self},
-    pkey, sign,
+    md, pkey, pkey_ctx, sign, symm,
 };
 
-use super::algorithm::{CryptoAdaptor, KeyAlgorithmFactory};
-use crate::common::errors::Result;
+use super::{
+    algorithm::{CryptoAdaptor, KeyAlgorithmFactory, AEAD_TAG_SIZE},
+    ecdh,
+};
+use crate::common::errors::{Result, ServiceError};
 
 pub struct EcAlgorithmFactory {}
 
 impl EcAlgorithmFactory {}
 
+// ECIES_HKDF_SHA_256_AES_GCM, everything a client needs to encrypt offline
+// to a kms public key:
+//   1. generate an ephemeral key pair on the curve of the kms key
+//   2. z = x coordinate of ecdh(ephemeral private key, kms public key)
+//   3. okm = hkdf-sha256(ikm = z, salt = R, info =
+//      "ECIES_HKDF_SHA_256_AES_GCM", length = 44), aes-256-gcm key = okm[0 ..
+//      32], nonce = okm[32 .. 44]
+//   4. aes-256-gcm over the plaintext with the caller aad
+// ciphertext layout: | R, uncompressed sec1 point | ciphertext ... | tag (16) |
+pub struct EciesAlgorithmFactory {}
+
+const ECIES_INFO: &[u8] = b"ECIES_HKDF_SHA_256_AES_GCM";
+const ECIES_KEY_SIZE: usize = 32;
+const ECIES_NONCE_SIZE: usize = 12;
+
+pub fn hkdf_sha256(
+    ikm: &[u8],
+    salt: &[u8],
+    info: &[u8],
+    len: usize,
+) -> Result<Vec<u8>> {
+    let mut ctx = pkey_ctx::PkeyCtx::new_id(pkey::Id::HKDF)
+        .context("hkdf pkey context create failed")?;
+    ctx.derive_init().context("hkdf derive init failed")?;
+    ctx.set_hkdf_md(md::Md::sha256())
+        .context("hkdf set digest failed")?;
+    ctx.set_hkdf_key(ikm).context("hkdf set key failed")?;
+    ctx.set_hkdf_salt(salt).context("hkdf set salt failed")?;
+    ctx.add_hkdf_info(info).context("hkdf add info failed")?;
+    let mut okm = vec![0; len];
+    ctx.derive(Some(&mut okm)).context("hkdf derive failed")?;
+    Ok(okm)
+}
+
+impl EciesAlgorithmFactory {
+    fn aead_params(shared: &[u8], point: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        let mut okm = hkdf_sha256(
+            shared,
+            point,
+            ECIES_INFO,
+            ECIES_KEY_SIZE + ECIES_NONCE_SIZE,
+        )?;
+        let nonce = okm.split_off(ECIES_KEY_SIZE);
+        Ok((okm, nonce))
+    }
+}
+
 impl KeyAlgorithmFactory for EcAlgorithmFactory {
     fn sign(
         &self,
@@ -20,8 +71,22 @@ impl KeyAlgorithmFactory for EcAlgorithmFactory {
     ) -> Result<Vec<u8>> {
         let pkey = pkey::PKey::private_key_from_pkcs8(pri_key)
             .context("import ec private key pkcs8 to pkey failed")?;
-        let mut signer = sign::Signer::new(e.md.unwrap(), &pkey)
-            .context("pkey tansform to signer failed")?;
+
+        if e.prehashed {
+            let mut ctx = pkey_ctx::PkeyCtx::new(&pkey)
+                .context("pkey tansform to ec pkey ctx failed")?;
+            ctx.sign_init().context("ec pkey ctx sign init failed")?;
+            let mut signature = vec![];
+            ctx.sign_to_vec(plaintext, &mut signature)
+                .context("ec pkey ctx sign digest failed")?;
+            return Ok(signature);
+        }
+
+        let mut signer = sign::Signer::new(
+            e.md.context("ec sign requires message digest")?,
+            &pkey,
+        )
+        .context("pkey tansform to signer failed")?;
         signer
             .update(plaintext)
             .context("ec update plaintext failed")?;
@@ -38,14 +103,25 @@ impl KeyAlgorithmFactory for EcAlgorithmFactory {
     ) -> Result<bool> {
         let pkey =
             pkey::PKey::public_key_from_der(pub_key).context("import")?;
-        let mut verifier = sign::Verifier::new(e.md.unwrap(), &pkey)
-            .context("import public key to ec pkey failed")?;
+
+        if e.prehashed {
+            let mut ctx = pkey_ctx::PkeyCtx::new(&pkey)
+                .context("pkey tansform to ec pkey ctx failed")?;
+            ctx.verify_init()
+                .context("ec pkey ctx verify init failed")?;
+            // a malformed signature is reported as an error by openssl
+            return Ok(ctx.verify(plaintext, signature).unwrap_or(false));
+        }
+
+        let mut verifier = sign::Verifier::new(
+            e.md.context("ec verify requires message digest")?,
+            &pkey,
+        )
+        .context("import public key to ec pkey failed")?;
         verifier
             .update(plaintext)
             .context("ec verifier update plaintext failed")?;
-        Ok(verifier
-            .verify(signature)
-            .context("ec verifier veirify failed")?)
+        Ok(verifier.verify(signature).unwrap_or(false))
     }
 
     fn encrypt(
@@ -95,18 +171,159 @@ impl KeyAlgorithmFactory for EcAlgorithmFactory {
     }
 }
 
+impl KeyAlgorithmFactory for EciesAlgorithmFactory {
+    fn encrypt(
+        &self,
+        pub_key: &[u8],
+        plaintext: &[u8],
+        e: &mut CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        let recipient = pkey::PKey::public_key_from_der(pub_key)
+            .context("import ec public key to pkey failed")?;
+        let recipient_ec = recipient
+            .ec_key()
+            .context("ecies requires an ec public key")?;
+        let group = recipient_ec.group();
+        let ephemeral = ec::EcKey::generate(group)
+            .context("generate ecies ephemeral key failed")?;
+        let mut ctx =
+            bn::BigNumContext::new().context("bignum context create failed")?;
+        let point = ephemeral
+            .public_key()
+            .to_bytes(group, ec::PointConversionForm::UNCOMPRESSED, &mut ctx)
+            .context("export ecies ephemeral point failed")?;
+        let ephemeral = pkey::PKey::from_ec_key(ephemeral)
+            .context("ecies ephemeral key transfer to pkey failed")?;
+
+        let shared = ecdh::agree(&ephemeral, &recipient)?;
+        let (key, nonce) = Self::aead_params(&shared, &point)?;
+        let mut tag = vec![0; AEAD_TAG_SIZE];
+        let ciphertext = symm::encrypt_aead(
+            symm::Cipher::aes_256_gcm(),
+            &key,
+            Some(&nonce),
+            &e.kits
+                .as_ref()
+                .map(|kits| kits.aad.clone())
+                .unwrap_or_default(),
+            plaintext,
+            &mut tag,
+        )
+        .context("ecies aead encrypt failed")?;
+        Ok([point, ciphertext, tag].concat())
+    }
+
+    fn decrypt(
+        &self,
+        private_key: &[u8],
+        cipher: &[u8],
+        e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        let private = pkey::PKey::private_key_from_pkcs8(private_key)
+            .context("import ec private key pkcs8 to pkey failed")?;
+        let private_ec = private
+            .ec_key()
+            .context("ecies requires an ec private key")?;
+        let group = private_ec.group();
+        let point_size = 1 + 2 * (group.degree() as usize).div_ceil(8);
+        if cipher.len() < point_size + AEAD_TAG_SIZE {
+            return Err(ServiceError::BadRequest(
+                "ecies ciphertext is truncated".to_owned(),
+            ));
+        }
+        let (point, rest) = cipher.split_at(point_size);
+        let (ciphertext, tag) = rest.split_at(rest.len() - AEAD_TAG_SIZE);
+        let ephemeral = bn::BigNumContext::new()
+            .and_then(|mut ctx| ec::EcPoint::from_bytes(group, point, &mut ctx))
+            .and_then(|point| ec::EcKey::from_public_key(group, &point))
+            .and_then(pkey::PKey::from_ec_key)
+            .map_err(|_| {
+                ServiceError::BadRequest(
+                    "ecies ephemeral point is invalid".to_owned(),
+                )
+            })?;
+
+        let shared = ecdh::agree(&private, &ephemeral)?;
+        let (key, nonce) = Self::aead_params(&shared, point)?;
+        Ok(symm::decrypt_aead(
+            symm::Cipher::aes_256_gcm(),
+            &key,
+            Some(&nonce),
+            &e.kits
+                .as_ref()
+                .map(|kits| kits.aad.clone())
+                .unwrap_or_default(),
+            ciphertext,
+            tag,
+        )
+        .context("ecies aead decrypt failed")?)
+    }
+
+    fn sign(
+        &self,
+        _pri_key: &[u8],
+        _plaintext: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        Err(ServiceError::Unsupported(
+            "ecies is unsupported sign action".to_owned(),
+        ))
+    }
+
+    fn verify(
+        &self,
+        _pub_key: &[u8],
+        _plaintext: &[u8],
+        _signature: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<bool> {
+        Err(ServiceError::Unsupported(
+            "ecies is unsupported verify action".to_owned(),
+        ))
+    }
+}
+
 #[cfg(test)]
 
 mod tests {
-    use super::EcAlgorithmFactory;
+    use super::{EcAlgorithmFactory, EciesAlgorithmFactory};
     use crate::{
         common::utils,
         crypto::{
             algorithm::{self, KeyAlgorithmFactory},
-            types::{WrappingKeyAlgorithm, WrappingKeySpec},
+            types::{
+                KeyAlgorithm, KeySpec, WrappingKeyAlgorithm, WrappingKeySpec,
+            },
         },
     };
 
+    #[test]
+    fn test_ecies() {
+        let factory = EciesAlgorithmFactory {};
+        for (spec, point_size) in [(KeySpec::EcP256, 65), (KeySpec::EcP384, 97)]
+        {
+            let (private, public) = algorithm::generate_key(spec).unwrap();
+            let mut adaptor = algorithm::select_encrypt_adaptor(
+                KeyAlgorithm::EciesHkdfAesGCM,
+                &public,
+                b"aad".to_vec(),
+                false,
+            )
+            .unwrap();
+            let cipher = factory
+                .encrypt(&public, b"plaintext", &mut adaptor)
+                .unwrap();
+            assert_eq!(cipher.len(), point_size + 9 + 16);
+            assert_eq!(cipher[0], 0x04);
+            assert_eq!(
+                factory.decrypt(&private, &cipher, &adaptor).unwrap(),
+                b"plaintext"
+            );
+            adaptor.kits.as_mut().unwrap().aad = b"tampered".to_vec();
+            assert!(factory.decrypt(&private, &cipher, &adaptor).is_err());
+        }
+    }
+
     #[test]
     fn test_encrypt_decrypt_sm2() {
         let ec_f = EcAlgorithmFactory {};
@@ -139,4 +356,15 @@ mod tests {
             b"plaintext"
         );
     }
+
+    #[test]
+    fn test_sm2_wrapping_key() {
+        let (private, public) =
+            algorithm::generate_wrapping_key(WrappingKeySpec::EcSm2).unwrap();
+        // id-ecPublicKey with the sm2p256v1 curve oid 1.2.156.10197.1.301
+        let sm2_oid = hex::decode("06082a811ccf5501822d").unwrap();
+        for der in [&private, &public] {
+            assert!(der.windows(sm2_oid.len()).any(|w| w.eq(&sm2_oid)));
+        }
+    }
 }
@@ -0,0 +1,171 @@
+use anyhow::Context;
+use openssl::{bn, derive, ec, pkey};
+
+use super::types::KeySpec;
+use crate::common::errors::{Result, ServiceError};
+
+const PEM_PREFIX: &[u8] = b"-----BEGIN";
+
+// the shared secret is the raw output of the agreement, the x coordinate for
+// ec curves (sec1) and the u coordinate for x25519/x448 (rfc 7748), callers
+// are expected to feed it into a kdf rather than use it as a key directly
+pub fn agree<T: pkey::HasPrivate>(
+    private: &pkey::PKeyRef<T>,
+    peer: &pkey::PKeyRef<pkey::Public>,
+) -> Result<Vec<u8>> {
+    let mut deriver =
+        derive::Deriver::new(private).context("ecdh initialize failed")?;
+    deriver.set_peer(peer).map_err(|_| {
+        ServiceError::BadRequest(
+            "peer public key is unmatched with the key".to_owned(),
+        )
+    })?;
+    Ok(deriver.derive_to_vec().context("ecdh derive failed")?)
+}
+
+pub fn derive_shared_secret(
+    spec: KeySpec,
+    private_key: &[u8],
+    peer_public_key: &[u8],
+) -> Result<Vec<u8>> {
+    let private = pkey::PKey::private_key_from_pkcs8(private_key)
+        .context("import ecdh private key pkcs8 to pkey failed")?;
+    let peer = import_peer_public_key(spec, peer_public_key)?;
+    agree(&private, &peer)
+}
+
+// the peer public key is accepted as pem, der spki, or the raw key: a sec1
+// point for ec curves and the 32/56 bytes u coordinate for x25519/x448
+pub fn import_peer_public_key(
+    spec: KeySpec,
+    peer: &[u8],
+) -> Result<pkey::PKey<pkey::Public>> {
+    let (nid, size) = spec.into();
+    let pkey = if peer.starts_with(PEM_PREFIX) {
+        pkey::PKey::public_key_from_pem(peer).ok()
+    } else {
+        pkey::PKey::public_key_from_der(peer).ok()
+    };
+    let pkey = match (pkey, spec) {
+        (Some(pkey), _) => pkey,
+        (None, KeySpec::X25519 | KeySpec::X448) if peer.len() == size => {
+            pkey::PKey::public_key_from_raw_bytes(
+                peer,
+                pkey::Id::from_raw(nid.as_raw()),
+            )
+            .context("import raw peer public key failed")?
+        }
+        (
+            None,
+            KeySpec::EcP256
+            | KeySpec::EcP256K
+            | KeySpec::EcP384
+            | KeySpec::EcP521,
+        ) => ec::EcGroup::from_curve_name(nid)
+            .and_then(|group| {
+                let mut ctx = bn::BigNumContext::new()?;
+                let point = ec::EcPoint::from_bytes(&group, peer, &mut ctx)?;
+                ec::EcKey::from_public_key(&group, &point)
+            })
+            .and_then(pkey::PKey::from_ec_key)
+            .map_err(|_| {
+                ServiceError::BadRequest(
+                    "peer public key is neither pem, der nor a sec1 point"
+                        .to_owned(),
+                )
+            })?,
+        (None, _) => {
+            return Err(ServiceError::BadRequest(format!(
+                "peer public key is unrecognized, spec: {:?}",
+                spec
+            )))
+        }
+    };
+    let matched = match spec {
+        KeySpec::X25519 | KeySpec::X448 => nid.as_raw() == pkey.id().as_raw(),
+        _ => pkey
+            .ec_key()
+            .map(|ec_key| Some(nid) == ec_key.group().curve_name())
+            .unwrap_or(false),
+    };
+    if !matched {
+        return Err(ServiceError::BadRequest(format!(
+            "peer public key is not a {:?} key",
+            spec
+        )));
+    }
+    Ok(pkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{bn, ec, pkey};
+
+    use super::derive_shared_secret;
+    use crate::crypto::{algorithm, types::KeySpec};
+
+    #[test]
+    fn test_x25519_vector() {
+        // rfc 7748 section 6.1
+        let alice = pkey::PKey::private_key_from_raw_bytes(
+            &hex::decode(
+                "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a",
+            )
+            .unwrap(),
+            pkey::Id::X25519,
+        )
+        .unwrap();
+        let bob_public = hex::decode(
+            "de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f",
+        )
+        .unwrap();
+        let secret = derive_shared_secret(
+            KeySpec::X25519,
+            &alice.private_key_to_pkcs8().unwrap(),
+            &bob_public,
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(&secret),
+            "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742"
+        );
+    }
+
+    #[test]
+    fn test_ecdh_peer_formats() {
+        for spec in [KeySpec::EcP256, KeySpec::EcP384, KeySpec::X448] {
+            let (private, public) = algorithm::generate_key(spec).unwrap();
+            let (peer_private, peer_public) =
+                algorithm::generate_key(spec).unwrap();
+            let expected =
+                derive_shared_secret(spec, &peer_private, &public).unwrap();
+
+            let peer = pkey::PKey::public_key_from_der(&peer_public).unwrap();
+            let raw = match spec {
+                KeySpec::X448 => peer.raw_public_key().unwrap(),
+                _ => {
+                    let ec_key = peer.ec_key().unwrap();
+                    ec_key
+                        .public_key()
+                        .to_bytes(
+                            ec_key.group(),
+                            ec::PointConversionForm::COMPRESSED,
+                            &mut bn::BigNumContext::new().unwrap(),
+                        )
+                        .unwrap()
+                }
+            };
+            for encoded in
+                [peer_public.clone(), peer.public_key_to_pem().unwrap(), raw]
+            {
+                assert_eq!(
+                    derive_shared_secret(spec, &private, &encoded).unwrap(),
+                    expected
+                );
+            }
+            // a key on another curve is rejected
+            let (_, other) = algorithm::generate_key(KeySpec::EcP521).unwrap();
+            assert!(derive_shared_secret(spec, &private, &other).is_err());
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::Context;
+use openssl::{pkey, sign};
+
+use super::algorithm::{CryptoAdaptor, KeyAlgorithmFactory};
+use crate::common::errors::{Result, ServiceError};
+
+// pure eddsa (rfc 8032), the raw message is signed without a separate digest
+pub struct EdDsaAlgorithmFactory {}
+
+impl KeyAlgorithmFactory for EdDsaAlgorithmFactory {
+    fn sign(
+        &self,
+        pri_key: &[u8],
+        plaintext: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        let pkey = pkey::PKey::private_key_from_pkcs8(pri_key)
+            .context("import eddsa private key pkcs8 to pkey failed")?;
+        let mut signer = sign::Signer::new_without_digest(&pkey)
+            .context("pkey tansform to eddsa signer failed")?;
+        Ok(signer
+            .sign_oneshot_to_vec(plaintext)
+            .context("eddsa signer sign failed")?)
+    }
+
+    fn verify(
+        &self,
+        pub_key: &[u8],
+        plaintext: &[u8],
+        signature: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<bool> {
+        let pkey = pkey::PKey::public_key_from_der(pub_key)
+            .context("import eddsa public key to pkey failed")?;
+        let mut verifier = sign::Verifier::new_without_digest(&pkey)
+            .context("pkey tansform to eddsa verifier failed")?;
+        Ok(verifier
+            .verify_oneshot(signature, plaintext)
+            .unwrap_or(false))
+    }
+
+    fn encrypt(
+        &self,
+        _pub_key: &[u8],
+        _plaintext: &[u8],
+        _e: &mut CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        Err(ServiceError::Unsupported(
+            "eddsa is unsupported encrypt action".to_owned(),
+        ))
+    }
+
+    fn decrypt(
+        &self,
+        _private_key: &[u8],
+        _cipher: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        Err(ServiceError::Unsupported(
+            "eddsa is unsupported decrypt action".to_owned(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EdDsaAlgorithmFactory;
+    use crate::crypto::{
+        algorithm::{generate_key, CryptoAdaptor, KeyAlgorithmFactory},
+        types::{KeyAlgorithm, KeySpec},
+    };
+
+    #[test]
+    fn test_eddsa_sign_verify() {
+        let factory = EdDsaAlgorithmFactory {};
+        let adaptor: CryptoAdaptor = KeyAlgorithm::EdDSA.into();
+        for spec in [KeySpec::Ed25519, KeySpec::Ed448] {
+            let (pri_key, pub_key) = generate_key(spec).unwrap();
+            let signature =
+                factory.sign(&pri_key, b"raw message", &adaptor).unwrap();
+            assert!(factory
+                .verify(&pub_key, b"raw message", &signature, &adaptor)
+                .unwrap());
+            assert!(!factory
+                .verify(&pub_key, b"tampered message", &signature, &adaptor)
+                .unwrap());
+        }
+    }
+}
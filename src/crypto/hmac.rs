@@ -0,0 +1,113 @@
+use anyhow::Context;
+use openssl::{memcmp, pkey, sign};
+
+use super::algorithm::{CryptoAdaptor, KeyAlgorithmFactory};
+use crate::common::errors::{Result, ServiceError};
+
+// the symmetric key is both sides of the pair, sign generates the mac
+pub struct HmacAlgorithmFactory {}
+
+impl HmacAlgorithmFactory {
+    fn mac(key: &[u8], message: &[u8], e: &CryptoAdaptor) -> Result<Vec<u8>> {
+        let md = e.md.ok_or(ServiceError::Unsupported(
+            "hmac message digest is required".to_owned(),
+        ))?;
+        let pkey =
+            pkey::PKey::hmac(key).context("import hmac key to pkey failed")?;
+        let mut signer = sign::Signer::new(md, &pkey)
+            .context("pkey tansform to hmac signer failed")?;
+        Ok(signer
+            .sign_oneshot_to_vec(message)
+            .context("hmac generate failed")?)
+    }
+}
+
+impl KeyAlgorithmFactory for HmacAlgorithmFactory {
+    fn sign(
+        &self,
+        pri_key: &[u8],
+        plaintext: &[u8],
+        e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        Self::mac(pri_key, plaintext, e)
+    }
+
+    // constant time comparison, the length of mac is not secret
+    fn verify(
+        &self,
+        pub_key: &[u8],
+        plaintext: &[u8],
+        signature: &[u8],
+        e: &CryptoAdaptor,
+    ) -> Result<bool> {
+        let mac = Self::mac(pub_key, plaintext, e)?;
+        Ok(mac.len() == signature.len() && memcmp::eq(&mac, signature))
+    }
+
+    fn encrypt(
+        &self,
+        _pub_key: &[u8],
+        _plaintext: &[u8],
+        _e: &mut CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        Err(ServiceError::Unsupported(
+            "hmac is unsupported encrypt action".to_owned(),
+        ))
+    }
+
+    fn decrypt(
+        &self,
+        _private_key: &[u8],
+        _cipher: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        Err(ServiceError::Unsupported(
+            "hmac is unsupported decrypt action".to_owned(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HmacAlgorithmFactory;
+    use crate::crypto::{
+        algorithm::{generate_key, select_mac_adaptor, KeyAlgorithmFactory},
+        types::KeySpec,
+    };
+
+    #[test]
+    fn test_hmac_generate_verify() {
+        let factory = HmacAlgorithmFactory {};
+        for (spec, size) in [
+            (KeySpec::HmacSha256, 32),
+            (KeySpec::HmacSha384, 48),
+            (KeySpec::HmacSha512, 64),
+            (KeySpec::HmacSm3, 32),
+        ] {
+            let (key, _) = generate_key(spec).unwrap();
+            let adaptor = select_mac_adaptor(spec).unwrap();
+            let mac = factory.sign(&key, b"message", &adaptor).unwrap();
+            assert_eq!(mac.len(), size);
+            assert!(factory.verify(&key, b"message", &mac, &adaptor).unwrap());
+            assert!(!factory
+                .verify(&key, b"message", &mac[.. size - 1], &adaptor)
+                .unwrap());
+            assert!(!factory
+                .verify(&key, b"tampered", &mac, &adaptor)
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn test_hmac_sha256_vector() {
+        // rfc 4231 test case 2
+        let adaptor = select_mac_adaptor(KeySpec::HmacSha256).unwrap();
+        let mac = HmacAlgorithmFactory {}
+            .sign(b"Jefe", b"what do ya want for nothing?", &adaptor)
+            .unwrap();
+        assert_eq!(
+            hex::encode(mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
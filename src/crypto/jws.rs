@@ -0,0 +1,190 @@
+use anyhow::Context;
+use openssl::ecdsa;
+use serde_json::{json, Value};
+
+use super::{
+    algorithm,
+    types::{KeyAlgorithm, KeySpec, MessageDigest, MessageType},
+};
+use crate::common::{
+    errors::{Result, ServiceError},
+    utils,
+};
+
+// the jws "alg" (rfc 7518, rfc 8037, rfc 8812) of a key spec and algorithm,
+// every jws algorithm but EdDSA is bound to sha-256
+pub fn jws_algorithm(
+    spec: KeySpec,
+    key_alg: KeyAlgorithm,
+) -> Result<&'static str> {
+    Ok(match (spec, key_alg) {
+        (
+            KeySpec::Rsa2048 | KeySpec::Rsa3072 | KeySpec::Rsa4096,
+            KeyAlgorithm::RsaPKCS1,
+        ) => "RS256",
+        (
+            KeySpec::Rsa2048 | KeySpec::Rsa3072 | KeySpec::Rsa4096,
+            KeyAlgorithm::RsaPSS,
+        ) => "PS256",
+        (KeySpec::EcP256, KeyAlgorithm::Ecdsa) => "ES256",
+        (KeySpec::EcP256K, KeyAlgorithm::Ecdsa) => "ES256K",
+        (KeySpec::Ed25519 | KeySpec::Ed448, KeyAlgorithm::EdDSA) => "EdDSA",
+        _ => {
+            return Err(ServiceError::Unsupported(format!(
+                "jws is unsupported by {:?} of key spec {:?}",
+                key_alg, spec
+            )))
+        }
+    })
+}
+
+// compact serialization, BASE64URL(header).BASE64URL(claims).BASE64URL(sig)
+pub fn sign_compact(
+    spec: KeySpec,
+    key_alg: KeyAlgorithm,
+    private_key: &[u8],
+    kid: &str,
+    claims: &Value,
+) -> Result<String> {
+    if !claims.is_object() {
+        return Err(ServiceError::BadRequest(
+            "jwt claims must be a json object".to_owned(),
+        ));
+    }
+    let header = json!({
+        "alg": jws_algorithm(spec, key_alg)?,
+        "typ": "JWT",
+        "kid": kid,
+    });
+    let signing_input = format!(
+        "{}.{}",
+        utils::encode64_url(header.to_string().as_bytes()),
+        utils::encode64_url(claims.to_string().as_bytes())
+    );
+    let digest = match key_alg {
+        KeyAlgorithm::EdDSA => None,
+        _ => Some(MessageDigest::Sha256),
+    };
+    let adaptor = algorithm::select_sign_adaptor(
+        spec,
+        key_alg,
+        digest,
+        MessageType::Raw,
+    )?;
+    let mut signature = algorithm::select_factory(key_alg)?.sign(
+        private_key,
+        signing_input.as_bytes(),
+        &adaptor,
+    )?;
+    if KeyAlgorithm::Ecdsa.eq(&key_alg) {
+        signature = ecdsa_der_to_raw(&signature, 32)?;
+    }
+    Ok(format!(
+        "{}.{}",
+        signing_input,
+        utils::encode64_url(&signature)
+    ))
+}
+
+// jws carries ecdsa signatures as the fixed size r || s instead of der
+fn ecdsa_der_to_raw(signature: &[u8], size: i32) -> Result<Vec<u8>> {
+    let signature = ecdsa::EcdsaSig::from_der(signature)
+        .context("decode ecdsa signature der failed")?;
+    Ok([
+        signature
+            .r()
+            .to_vec_padded(size)
+            .context("export ecdsa signature r failed")?,
+        signature
+            .s()
+            .to_vec_padded(size)
+            .context("export ecdsa signature s failed")?,
+    ]
+    .concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{bn, ecdsa, hash, pkey, rsa, sign};
+    use serde_json::{json, Value};
+
+    use super::sign_compact;
+    use crate::{
+        common::utils,
+        crypto::{
+            algorithm,
+            types::{KeyAlgorithm, KeySpec},
+        },
+    };
+
+    #[test]
+    fn test_sign_compact() {
+        let claims = json!({"sub": "1234567890", "iat": 1516239022});
+        for (spec, key_alg, alg) in [
+            (KeySpec::Rsa2048, KeyAlgorithm::RsaPKCS1, "RS256"),
+            (KeySpec::Rsa2048, KeyAlgorithm::RsaPSS, "PS256"),
+            (KeySpec::EcP256, KeyAlgorithm::Ecdsa, "ES256"),
+            (KeySpec::EcP256K, KeyAlgorithm::Ecdsa, "ES256K"),
+            (KeySpec::Ed25519, KeyAlgorithm::EdDSA, "EdDSA"),
+        ] {
+            let (private_key, public_key) =
+                algorithm::generate_key(spec).unwrap();
+            let jws = sign_compact(spec, key_alg, &private_key, "k:v", &claims)
+                .unwrap();
+            let parts = jws.split('.').collect::<Vec<_>>();
+            assert_eq!(parts.len(), 3);
+            let header: Value =
+                serde_json::from_slice(&utils::decode64_url(parts[0]).unwrap())
+                    .unwrap();
+            assert_eq!(header["alg"], alg);
+            assert_eq!(header["kid"], "k:v");
+            let payload: Value =
+                serde_json::from_slice(&utils::decode64_url(parts[1]).unwrap())
+                    .unwrap();
+            assert_eq!(payload, claims);
+
+            // verified independently of the kms factories
+            let input = format!("{}.{}", parts[0], parts[1]);
+            let mut signature = utils::decode64_url(parts[2]).unwrap();
+            let pkey = pkey::PKey::public_key_from_der(&public_key).unwrap();
+            let mut verifier = match key_alg {
+                KeyAlgorithm::EdDSA => {
+                    sign::Verifier::new_without_digest(&pkey).unwrap()
+                }
+                _ => sign::Verifier::new(hash::MessageDigest::sha256(), &pkey)
+                    .unwrap(),
+            };
+            match key_alg {
+                KeyAlgorithm::RsaPSS => {
+                    verifier.set_rsa_padding(rsa::Padding::PKCS1_PSS).unwrap();
+                    verifier
+                        .set_rsa_pss_saltlen(sign::RsaPssSaltlen::DIGEST_LENGTH)
+                        .unwrap();
+                }
+                KeyAlgorithm::Ecdsa => {
+                    assert_eq!(signature.len(), 64);
+                    signature = ecdsa::EcdsaSig::from_private_components(
+                        bn::BigNum::from_slice(&signature[.. 32]).unwrap(),
+                        bn::BigNum::from_slice(&signature[32 ..]).unwrap(),
+                    )
+                    .and_then(|signature| signature.to_der())
+                    .unwrap();
+                }
+                _ => {}
+            }
+            assert!(verifier
+                .verify_oneshot(&signature, input.as_bytes())
+                .unwrap());
+        }
+        let (private_key, _) =
+            algorithm::generate_key(KeySpec::EcP384).unwrap();
+        assert!(sign_compact(
+            KeySpec::EcP384,
+            KeyAlgorithm::Ecdsa,
+            &private_key,
+            "",
+            &claims
+        )
+        .is_err());
+    }
+}
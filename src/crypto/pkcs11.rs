@@ -0,0 +1,181 @@
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context};
+use cryptoki::{
+    context::{CInitializeArgs, Pkcs11},
+    mechanism::{aead::GcmParams, Mechanism},
+    object::{Attribute, KeyType, ObjectClass, ObjectHandle},
+    session::{Session, UserType},
+    types::AuthPin,
+};
+
+use super::root_key::{RootKeyProvider, ROOT_KEY_SIZE};
+use crate::common::{
+    configs::{env_var, env_var_default},
+    errors::{Result, ServiceError},
+    utils,
+};
+
+const PKCS11_IV_SIZE: usize = 12;
+const PKCS11_TAG_BITS: u64 = 128;
+
+// aes-256-gcm inside a pkcs#11 token, the root key never leaves the token,
+// wrapped material layout: | iv (12) | ciphertext ... | tag (16) |
+pub struct Pkcs11Provider {
+    session: Mutex<Session>,
+    key: ObjectHandle,
+}
+
+impl Pkcs11Provider {
+    pub fn new(
+        module: &str,
+        slot: u64,
+        pin: &str,
+        label: &str,
+    ) -> Result<Self> {
+        let pkcs11 = Pkcs11::new(module)
+            .context(format!("load pkcs11 module failed: {}", module))?;
+        pkcs11
+            .initialize(CInitializeArgs::OsThreads)
+            .context("initialize pkcs11 module failed")?;
+        let slot = pkcs11
+            .get_slots_with_token()
+            .context("list pkcs11 slots failed")?
+            .into_iter()
+            .find(|s| s.id() == slot)
+            .ok_or(ServiceError::BadRequest(format!(
+                "pkcs11 slot is nonexistent, slot: {}",
+                slot
+            )))?;
+        let session = pkcs11
+            .open_rw_session(slot)
+            .context("open pkcs11 session failed")?;
+        session
+            .login(UserType::User, Some(&AuthPin::new(pin.to_owned())))
+            .context("login pkcs11 token failed")?;
+        let key = Self::find_or_generate_key(&session, label)?;
+        Ok(Pkcs11Provider {
+            session: Mutex::new(session),
+            key,
+        })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        Self::new(
+            &env_var::<String>("PKCS11_MODULE"),
+            env_var::<u64>("PKCS11_SLOT"),
+            &env_var::<String>("PKCS11_PIN"),
+            &env_var_default::<String>(
+                "PKCS11_KEY_LABEL",
+                "kms-root-key".to_owned(),
+            ),
+        )
+    }
+
+    fn find_or_generate_key(
+        session: &Session,
+        label: &str,
+    ) -> Result<ObjectHandle> {
+        let template = vec![
+            Attribute::Class(ObjectClass::SECRET_KEY),
+            Attribute::KeyType(KeyType::AES),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ];
+        if let Some(key) = session
+            .find_objects(&template)
+            .context(format!("find pkcs11 root key failed: {}", label))?
+            .into_iter()
+            .next()
+        {
+            return Ok(key);
+        }
+        tracing::warn!("pkcs11 root key is absent, generate: {}", label);
+        Ok(session
+            .generate_key(&Mechanism::AesKeyGen, &[
+                Attribute::Token(true),
+                Attribute::Private(true),
+                Attribute::Sensitive(true),
+                Attribute::Extractable(false),
+                Attribute::Encrypt(true),
+                Attribute::Decrypt(true),
+                Attribute::ValueLen((ROOT_KEY_SIZE as u64).into()),
+                Attribute::Label(label.as_bytes().to_vec()),
+            ])
+            .context(format!("generate pkcs11 root key failed: {}", label))?)
+    }
+}
+
+impl RootKeyProvider for Pkcs11Provider {
+    fn wrap(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let iv = utils::generate_key(PKCS11_IV_SIZE)?;
+        let ciphertext = self
+            .session
+            .lock()
+            .unwrap()
+            .encrypt(
+                &Mechanism::AesGcm(GcmParams::new(
+                    &iv,
+                    aad,
+                    PKCS11_TAG_BITS.into(),
+                )),
+                self.key,
+                plaintext,
+            )
+            .context("pkcs11 root key wrap failed")?;
+        Ok([iv, ciphertext].concat())
+    }
+
+    fn unwrap(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < PKCS11_IV_SIZE + PKCS11_TAG_BITS as usize / 8 {
+            return Err(ServiceError::InternalServer(anyhow!(
+                "wrapped key material is truncated"
+            )));
+        }
+        let (iv, ciphertext) = ciphertext.split_at(PKCS11_IV_SIZE);
+        Ok(self
+            .session
+            .lock()
+            .unwrap()
+            .decrypt(
+                &Mechanism::AesGcm(GcmParams::new(
+                    iv,
+                    aad,
+                    PKCS11_TAG_BITS.into(),
+                )),
+                self.key,
+                ciphertext,
+            )
+            .context("pkcs11 root key unwrap failed")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pkcs11Provider;
+    use crate::{
+        common::configs::env_var_default, crypto::root_key::RootKeyProvider,
+    };
+
+    // softhsm2-util --init-token --free --label kms --so-pin 0000 --pin 1234
+    // PKCS11_SLOT=<slot> cargo test pkcs11 -- --ignored
+    #[test]
+    #[ignore = "requires an initialized softhsm2 token"]
+    fn test_softhsm_wrap_unwrap() {
+        let provider = Pkcs11Provider::new(
+            &env_var_default::<String>(
+                "PKCS11_MODULE",
+                "/usr/lib/softhsm/libsofthsm2.so".to_owned(),
+            ),
+            env_var_default::<u64>("PKCS11_SLOT", 0),
+            &env_var_default::<String>("PKCS11_PIN", "1234".to_owned()),
+            "kms-root-key-test",
+        )
+        .unwrap();
+        let wrapped = provider.wrap(b"key material", b"key_id:v1").unwrap();
+        assert_eq!(
+            provider.unwrap(&wrapped, b"key_id:v1").unwrap(),
+            b"key material"
+        );
+        assert!(provider.unwrap(&wrapped, b"key_id:v2").is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use anyhow::Context;
+use openssl::{bn, ec, pkey};
+use serde_json::{json, Value};
+
+use super::types::{KeySpec, KeyUsage, PublicKeyFormat};
+use crate::common::{
+    errors::{Result, ServiceError},
+    utils,
+};
+
+// the kid of jwk and jws headers, one per key version
+pub fn key_version_kid(key_id: &str, version: &str) -> String {
+    format!("{}:{}", key_id, version)
+}
+
+// the public key is stored as der spki, the other formats are transcoded
+// from it
+pub fn encode_public_key(
+    spec: KeySpec,
+    usage: KeyUsage,
+    public_key: &[u8],
+    format: PublicKeyFormat,
+    kid: &str,
+) -> Result<Value> {
+    Ok(match format {
+        PublicKeyFormat::Der => json!(utils::encode64(public_key)),
+        PublicKeyFormat::Pem => {
+            let pem = pkey::PKey::public_key_from_der(public_key)
+                .and_then(|pkey| pkey.public_key_to_pem())
+                .context("export public key pem failed")?;
+            json!(String::from_utf8(pem)
+                .context("public key pem is not utf-8")?)
+        }
+        PublicKeyFormat::Jwk => to_jwk(spec, usage, public_key, kid)?,
+        PublicKeyFormat::OpenSsh => json!(to_openssh(spec, public_key)?),
+    })
+}
+
+// rfc 7517/7518 for rsa and nist curves, rfc 8037 for okp curves, secp256k1
+// is named by rfc 8812, sm2 has no registered curve name
+pub fn to_jwk(
+    spec: KeySpec,
+    usage: KeyUsage,
+    public_key: &[u8],
+    kid: &str,
+) -> Result<Value> {
+    let pkey = pkey::PKey::public_key_from_der(public_key)
+        .context("import public key der failed")?;
+    let mut jwk = match spec {
+        KeySpec::Rsa2048 | KeySpec::Rsa3072 | KeySpec::Rsa4096 => {
+            let rsa = pkey.rsa().context("public key is not a rsa key")?;
+            json!({
+                "kty": "RSA",
+                "n": utils::encode64_url(&rsa.n().to_vec()),
+                "e": utils::encode64_url(&rsa.e().to_vec()),
+            })
+        }
+        KeySpec::EcP256
+        | KeySpec::EcP256K
+        | KeySpec::EcP384
+        | KeySpec::EcP521 => {
+            let (x, y) = ec_coordinates(&pkey)?;
+            json!({
+                "kty": "EC",
+                "crv": match spec {
+                    KeySpec::EcP256 => "P-256",
+                    KeySpec::EcP256K => "secp256k1",
+                    KeySpec::EcP384 => "P-384",
+                    _ => "P-521",
+                },
+                "x": utils::encode64_url(&x),
+                "y": utils::encode64_url(&y),
+            })
+        }
+        KeySpec::Ed25519 | KeySpec::Ed448 | KeySpec::X25519 | KeySpec::X448 => {
+            let x = pkey
+                .raw_public_key()
+                .context("export raw public key failed")?;
+            json!({
+                "kty": "OKP",
+                "crv": match spec {
+                    KeySpec::Ed25519 => "Ed25519",
+                    KeySpec::Ed448 => "Ed448",
+                    KeySpec::X25519 => "X25519",
+                    _ => "X448",
+                },
+                "x": utils::encode64_url(&x),
+            })
+        }
+        _ => {
+            return Err(ServiceError::Unsupported(format!(
+                "jwk is unsupported by key spec {:?}",
+                spec
+            )))
+        }
+    };
+    jwk["kid"] = json!(kid);
+    jwk["use"] = json!(match usage {
+        KeyUsage::SignAndVerify => "sig",
+        _ => "enc",
+    });
+    Ok(jwk)
+}
+
+// single line authorized_keys form, the key blob is rfc 4253 (ssh-rsa),
+// rfc 5656 (ecdsa-sha2-*) and rfc 8709 (ssh-ed25519/ssh-ed448)
+pub fn to_openssh(spec: KeySpec, public_key: &[u8]) -> Result<String> {
+    let pkey = pkey::PKey::public_key_from_der(public_key)
+        .context("import public key der failed")?;
+    let mut blob = vec![];
+    let name = match spec {
+        KeySpec::Rsa2048 | KeySpec::Rsa3072 | KeySpec::Rsa4096 => {
+            let rsa = pkey.rsa().context("public key is not a rsa key")?;
+            ssh_string(&mut blob, b"ssh-rsa");
+            ssh_mpint(&mut blob, &rsa.e().to_vec());
+            ssh_mpint(&mut blob, &rsa.n().to_vec());
+            "ssh-rsa"
+        }
+        KeySpec::EcP256 | KeySpec::EcP384 | KeySpec::EcP521 => {
+            let (name, curve) = match spec {
+                KeySpec::EcP256 => ("ecdsa-sha2-nistp256", "nistp256"),
+                KeySpec::EcP384 => ("ecdsa-sha2-nistp384", "nistp384"),
+                _ => ("ecdsa-sha2-nistp521", "nistp521"),
+            };
+            let ec_key =
+                pkey.ec_key().context("public key is not an ec key")?;
+            let point = bn::BigNumContext::new()
+                .and_then(|mut ctx| {
+                    ec_key.public_key().to_bytes(
+                        ec_key.group(),
+                        ec::PointConversionForm::UNCOMPRESSED,
+                        &mut ctx,
+                    )
+                })
+                .context("export ec public point failed")?;
+            ssh_string(&mut blob, name.as_bytes());
+            ssh_string(&mut blob, curve.as_bytes());
+            ssh_string(&mut blob, &point);
+            name
+        }
+        KeySpec::Ed25519 | KeySpec::Ed448 => {
+            let name = match spec {
+                KeySpec::Ed25519 => "ssh-ed25519",
+                _ => "ssh-ed448",
+            };
+            ssh_string(&mut blob, name.as_bytes());
+            ssh_string(
+                &mut blob,
+                &pkey
+                    .raw_public_key()
+                    .context("export raw public key failed")?,
+            );
+            name
+        }
+        _ => {
+            return Err(ServiceError::Unsupported(format!(
+                "openssh is unsupported by key spec {:?}",
+                spec
+            )))
+        }
+    };
+    Ok(format!("{} {}", name, utils::encode64(&blob)))
+}
+
+// affine coordinates left padded to the field size
+fn ec_coordinates(
+    pkey: &pkey::PKey<pkey::Public>,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let ec_key = pkey.ec_key().context("public key is not an ec key")?;
+    let size = (ec_key.group().degree() as i32 + 7) / 8;
+    let mut x = bn::BigNum::new().context("bignum create failed")?;
+    let mut y = bn::BigNum::new().context("bignum create failed")?;
+    bn::BigNumContext::new()
+        .and_then(|mut ctx| {
+            ec_key.public_key().affine_coordinates(
+                ec_key.group(),
+                &mut x,
+                &mut y,
+                &mut ctx,
+            )
+        })
+        .context("export ec affine coordinates failed")?;
+    Ok((
+        x.to_vec_padded(size)
+            .context("export x coordinate failed")?,
+        y.to_vec_padded(size)
+            .context("export y coordinate failed")?,
+    ))
+}
+
+fn ssh_string(blob: &mut Vec<u8>, value: &[u8]) {
+    blob.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    blob.extend_from_slice(value);
+}
+
+// two's complement, a positive value with the high bit set gets a zero byte
+fn ssh_mpint(blob: &mut Vec<u8>, value: &[u8]) {
+    match value.first() {
+        Some(first) if first & 0x80 != 0 => {
+            ssh_string(blob, &[&[0], value].concat())
+        }
+        _ => ssh_string(blob, value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{bn, pkey, rsa};
+
+    use super::{to_jwk, to_openssh};
+    use crate::{
+        common::utils,
+        crypto::{
+            algorithm,
+            types::{KeySpec, KeyUsage},
+        },
+    };
+
+    #[test]
+    fn test_jwk() {
+        let (_, public_key) = algorithm::generate_key(KeySpec::EcP384).unwrap();
+        let jwk = to_jwk(
+            KeySpec::EcP384,
+            KeyUsage::SignAndVerify,
+            &public_key,
+            "k:v",
+        )
+        .unwrap();
+        assert_eq!(jwk["kty"], "EC");
+        assert_eq!(jwk["crv"], "P-384");
+        assert_eq!(jwk["kid"], "k:v");
+        assert_eq!(jwk["use"], "sig");
+        for coordinate in ["x", "y"] {
+            let value =
+                utils::decode64_url(jwk[coordinate].as_str().unwrap()).unwrap();
+            assert_eq!(value.len(), 48);
+        }
+
+        // e = 65537 is "AQAB"
+        let (_, public_key) =
+            algorithm::generate_key(KeySpec::Rsa2048).unwrap();
+        let jwk = to_jwk(
+            KeySpec::Rsa2048,
+            KeyUsage::EncryptAndDecrypt,
+            &public_key,
+            "k:v",
+        )
+        .unwrap();
+        assert_eq!(jwk["e"], "AQAB");
+        assert_eq!(jwk["use"], "enc");
+
+        let (_, public_key) = algorithm::generate_key(KeySpec::EcSm2).unwrap();
+        assert!(to_jwk(
+            KeySpec::EcSm2,
+            KeyUsage::SignAndVerify,
+            &public_key,
+            ""
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_openssh() {
+        // rfc 8032 section 7.1 test 1
+        let public_key = pkey::PKey::public_key_from_raw_bytes(
+            &hex::decode(
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            )
+            .unwrap(),
+            pkey::Id::ED25519,
+        )
+        .unwrap()
+        .public_key_to_der()
+        .unwrap();
+        assert_eq!(
+            to_openssh(KeySpec::Ed25519, &public_key).unwrap(),
+            "ssh-ed25519 \
+             AAAAC3NzaC1lZDI1NTE5AAAAINdamAGCsQq31Uv+08lkBzoO4XLz2qYjJa8CGmj3B1Ea"
+        );
+
+        // n with the high bit set is prefixed by a zero byte
+        let rsa = rsa::Rsa::from_public_components(
+            bn::BigNum::from_u32(0x80).unwrap(),
+            bn::BigNum::from_u32(3).unwrap(),
+        )
+        .unwrap();
+        let public_key = pkey::PKey::from_rsa(rsa)
+            .unwrap()
+            .public_key_to_der()
+            .unwrap();
+        let openssh = to_openssh(KeySpec::Rsa2048, &public_key).unwrap();
+        let blob =
+            utils::decode64(openssh.strip_prefix("ssh-rsa ").unwrap()).unwrap();
+        assert_eq!(&blob[11 ..], &[0, 0, 0, 1, 3, 0, 0, 0, 2, 0, 0x80]);
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    fs,
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, Context};
+use lazy_static::lazy_static;
+use openssl::symm;
+
+use super::{algorithm::AEAD_TAG_SIZE, pkcs11::Pkcs11Provider};
+use crate::common::{
+    configs::env_var_default,
+    errors::{Result, ServiceError},
+    utils,
+};
+
+pub const ROOT_KEY_SIZE: usize = 32;
+const ROOT_IV_SIZE: usize = 12;
+
+lazy_static! {
+    static ref ROOT_KEY_PROVIDER: RwLock<Option<Arc<dyn RootKeyProvider>>> =
+        RwLock::new(None);
+}
+
+// wraps key material at rest, the aad binds the material to its owner
+pub trait RootKeyProvider: Send + Sync {
+    fn wrap(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>>;
+    fn unwrap(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>>;
+}
+
+// aes-256-gcm with a root key held in memory, wrapped material layout:
+// | iv (12) | tag (16) | ciphertext ... |
+pub struct MemoryRootKey {
+    key: Vec<u8>,
+}
+
+impl MemoryRootKey {
+    pub fn new(key: Vec<u8>) -> Result<Self> {
+        if ROOT_KEY_SIZE != key.len() {
+            return Err(ServiceError::BadRequest(format!(
+                "root key length is invalid, expect: {}, actual: {}",
+                ROOT_KEY_SIZE,
+                key.len()
+            )));
+        }
+        Ok(MemoryRootKey { key })
+    }
+}
+
+impl RootKeyProvider for MemoryRootKey {
+    fn wrap(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let iv = utils::generate_key(ROOT_IV_SIZE)?;
+        let mut tag = vec![0; AEAD_TAG_SIZE];
+        let ciphertext = symm::encrypt_aead(
+            symm::Cipher::aes_256_gcm(),
+            &self.key,
+            Some(&iv),
+            aad,
+            plaintext,
+            &mut tag,
+        )
+        .context("root key wrap failed")?;
+        Ok([iv, tag, ciphertext].concat())
+    }
+
+    fn unwrap(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < ROOT_IV_SIZE + AEAD_TAG_SIZE {
+            return Err(ServiceError::InternalServer(anyhow!(
+                "wrapped key material is truncated"
+            )));
+        }
+        let (iv, rest) = ciphertext.split_at(ROOT_IV_SIZE);
+        let (tag, ciphertext) = rest.split_at(AEAD_TAG_SIZE);
+        Ok(symm::decrypt_aead(
+            symm::Cipher::aes_256_gcm(),
+            &self.key,
+            Some(iv),
+            aad,
+            ciphertext,
+            tag,
+        )
+        .context("root key unwrap failed")?)
+    }
+}
+
+// base64 root key stored in a local file, the file is created once by the
+// `init-root-key` command and never generated implicitly, a missing file
+// fails the start instead of running under a throwaway root key
+pub struct LocalFileProvider {
+    root_key: MemoryRootKey,
+}
+
+impl LocalFileProvider {
+    pub fn new(path: &Path) -> Result<Self> {
+        let encoded = fs::read_to_string(path).context(format!(
+            "read root key file failed, run `init-root-key` on the first \
+             deploy: {:?}",
+            path
+        ))?;
+        Ok(LocalFileProvider {
+            root_key: MemoryRootKey::new(utils::decode64(encoded.trim())?)?,
+        })
+    }
+
+    // an existing root key file is never overwritten
+    pub fn generate(path: &Path) -> Result<()> {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)
+            .context(format!("create root key file failed: {:?}", path))?;
+        file.write_all(
+            utils::encode64(&utils::generate_key(ROOT_KEY_SIZE)?).as_bytes(),
+        )
+        .context(format!("write root key file failed: {:?}", path))?;
+        Ok(())
+    }
+}
+
+impl RootKeyProvider for LocalFileProvider {
+    fn wrap(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.root_key.wrap(plaintext, aad)
+    }
+
+    fn unwrap(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.root_key.unwrap(ciphertext, aad)
+    }
+}
+
+// base64 root key in an environment variable
+pub struct EnvProvider {
+    root_key: MemoryRootKey,
+}
+
+impl EnvProvider {
+    pub fn new(name: &str) -> Result<Self> {
+        let encoded = std::env::var(name).context(format!(
+            "root key environment variable is absent: {}",
+            name
+        ))?;
+        Ok(EnvProvider {
+            root_key: MemoryRootKey::new(utils::decode64(encoded.trim())?)?,
+        })
+    }
+}
+
+impl RootKeyProvider for EnvProvider {
+    fn wrap(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.root_key.wrap(plaintext, aad)
+    }
+
+    fn unwrap(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.root_key.unwrap(ciphertext, aad)
+    }
+}
+
+pub fn init() -> Result<()> {
+    let provider: Arc<dyn RootKeyProvider> =
+        match env_var_default::<String>("ROOT_KEY_PROVIDER", "file".to_owned())
+            .as_str()
+        {
+            "file" => Arc::new(LocalFileProvider::new(&root_key_file())?),
+            "env" => Arc::new(EnvProvider::new("ROOT_KEY")?),
+            "pkcs11" => Arc::new(Pkcs11Provider::from_env()?),
+            // sealed until the root key is unsealed by shamir shares
+            "shamir" => {
+                tracing::warn!("root key is sealed, waiting for unseal");
+                return Ok(());
+            }
+            provider => {
+                return Err(ServiceError::Unsupported(format!(
+                    "unsupported root key provider: {}",
+                    provider
+                )))
+            }
+        };
+    install(provider);
+    Ok(())
+}
+
+pub fn root_key_file() -> PathBuf {
+    PathBuf::from(env_var_default::<String>(
+        "ROOT_KEY_FILE",
+        "./root.key".to_owned(),
+    ))
+}
+
+pub fn install(provider: Arc<dyn RootKeyProvider>) {
+    *ROOT_KEY_PROVIDER.write().unwrap() = Some(provider);
+}
+
+pub fn uninstall() {
+    *ROOT_KEY_PROVIDER.write().unwrap() = None;
+}
+
+pub fn is_sealed() -> bool {
+    ROOT_KEY_PROVIDER.read().unwrap().is_none()
+}
+
+fn provider() -> Result<Arc<dyn RootKeyProvider>> {
+    ROOT_KEY_PROVIDER
+        .read()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| ServiceError::Sealed("root key is sealed".to_owned()))
+}
+
+pub fn wrap(plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    provider()?.wrap(plaintext, aad)
+}
+
+pub fn unwrap(ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    provider()?.unwrap(ciphertext, aad)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::{
+        LocalFileProvider, MemoryRootKey, RootKeyProvider, ROOT_KEY_SIZE,
+    };
+    use crate::common::utils;
+
+    #[test]
+    fn test_wrap_unwrap() {
+        let root_key =
+            MemoryRootKey::new(utils::generate_key(ROOT_KEY_SIZE).unwrap())
+                .unwrap();
+        let wrapped = root_key.wrap(b"key material", b"key_id:v1").unwrap();
+        assert_eq!(
+            root_key.unwrap(&wrapped, b"key_id:v1").unwrap(),
+            b"key material"
+        );
+        // material of another key or version is rejected
+        assert!(root_key.unwrap(&wrapped, b"key_id:v2").is_err());
+    }
+
+    #[test]
+    fn test_local_file_provider() {
+        let path = std::env::temp_dir()
+            .join(format!("kms-root-key-{}", utils::uuid()));
+        // an absent file is never generated implicitly
+        assert!(LocalFileProvider::new(&path).is_err());
+        LocalFileProvider::generate(&path).unwrap();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        let wrapped = LocalFileProvider::new(&path)
+            .unwrap()
+            .wrap(b"key material", b"key_id:v1")
+            .unwrap();
+        // an existing file is never overwritten
+        assert!(LocalFileProvider::generate(&path).is_err());
+        assert_eq!(
+            LocalFileProvider::new(&path)
+                .unwrap()
+                .unwrap(&wrapped, b"key_id:v1")
+                .unwrap(),
+            b"key material"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,14 +1,26 @@
 use anyhow::Context;
 use openssl::{
     encrypt::{self},
-    pkey, sign,
+    hash, md, pkey, pkey_ctx, rsa, sign,
 };
 
-use super::algorithm::{CryptoAdaptor, KeyAlgorithmFactory};
-use crate::common::errors::Result;
+use super::{
+    algorithm::{CryptoAdaptor, KeyAlgorithmFactory},
+    symm::{key_unwrap_pad, key_wrap_pad},
+};
+use crate::common::{
+    errors::{Result, ServiceError},
+    utils,
+};
+
+const EPHEMERAL_KEY_SIZE: usize = 32;
 
 pub struct RsaAlgorithmFactory {}
 
+// CKM_RSA_AES_KEY_WRAP, the wrapped material layout:
+// | rsa-oaep(ephemeral aes key) (modulus size) | aes-kwp(material) ... |
+pub struct RsaAesKeyWrapFactory {}
+
 impl KeyAlgorithmFactory for RsaAlgorithmFactory {
     fn sign(
         &self,
@@ -18,13 +30,31 @@ impl KeyAlgorithmFactory for RsaAlgorithmFactory {
     ) -> Result<Vec<u8>> {
         let pkey = pkey::PKey::private_key_from_pkcs8(pri_key)
             .context("import rsa private key pkcs8 failed")?;
-        let mut signer = sign::Signer::new(e.md.unwrap(), &pkey)
+        let md = e.md.context("rsa sign requires message digest")?;
+
+        if e.prehashed {
+            let mut ctx = pkey_ctx::PkeyCtx::new(&pkey)
+                .context("pkey tansform to rsa pkey ctx failed")?;
+            ctx.sign_init().context("rsa pkey ctx sign init failed")?;
+            set_ctx_padding(&mut ctx, md, e.padding)?;
+            let mut signature = vec![];
+            ctx.sign_to_vec(plaintext, &mut signature)
+                .context("rsa pkey ctx sign digest failed")?;
+            return Ok(signature);
+        }
+
+        let mut signer = sign::Signer::new(md, &pkey)
             .context("pkey tansform to rsa signer failed")?;
 
         if let Some(pad) = e.padding {
             signer
                 .set_rsa_padding(pad)
                 .context(format!("rsa signer set padding failed, {:?}", pad))?;
+            if rsa::Padding::PKCS1_PSS.eq(&pad) {
+                signer
+                    .set_rsa_pss_saltlen(sign::RsaPssSaltlen::DIGEST_LENGTH)
+                    .context("rsa signer set pss salt length failed")?;
+            }
         }
         signer
             .update(plaintext)
@@ -42,23 +72,38 @@ impl KeyAlgorithmFactory for RsaAlgorithmFactory {
     ) -> Result<bool> {
         let pkey = pkey::PKey::public_key_from_der(pub_key)
             .context("import public key failed")?;
-        let mut verifier = sign::Verifier::new(e.md.unwrap(), &pkey)
-            .context("pkey tansform to verifer failed")?;
+        let md = e.md.context("rsa verify requires message digest")?;
 
-        verifier
-            .update(plaintext)
-            .context("rsa signer update plaintext failed")?;
+        if e.prehashed {
+            let mut ctx = pkey_ctx::PkeyCtx::new(&pkey)
+                .context("pkey tansform to rsa pkey ctx failed")?;
+            ctx.verify_init()
+                .context("rsa pkey ctx verify init failed")?;
+            set_ctx_padding(&mut ctx, md, e.padding)?;
+            // a malformed signature is reported as an error by openssl
+            return Ok(ctx.verify(plaintext, signature).unwrap_or(false));
+        }
+
+        let mut verifier = sign::Verifier::new(md, &pkey)
+            .context("pkey tansform to verifer failed")?;
 
         if let Some(pad) = e.padding {
             verifier.set_rsa_padding(pad).context(format!(
                 "rsa verifier set padding failed, {:?}",
                 pad
             ))?;
+            if rsa::Padding::PKCS1_PSS.eq(&pad) {
+                verifier
+                    .set_rsa_pss_saltlen(sign::RsaPssSaltlen::DIGEST_LENGTH)
+                    .context("rsa verifier set pss salt length failed")?;
+            }
         }
 
-        Ok(verifier
-            .verify(signature)
-            .context("rsa verifier verify failed")?)
+        verifier
+            .update(plaintext)
+            .context("rsa signer update plaintext failed")?;
+
+        Ok(verifier.verify(signature).unwrap_or(false))
     }
 
     fn encrypt(
@@ -139,3 +184,110 @@ impl KeyAlgorithmFactory for RsaAlgorithmFactory {
         Ok(to)
     }
 }
+
+fn set_ctx_padding<T>(
+    ctx: &mut pkey_ctx::PkeyCtx<T>,
+    md: hash::MessageDigest,
+    padding: Option<rsa::Padding>,
+) -> Result<()> {
+    if let Some(pad) = padding {
+        ctx.set_rsa_padding(pad)
+            .context(format!("rsa pkey ctx set padding failed, {:?}", pad))?;
+    }
+    ctx.set_signature_md(
+        md::Md::from_nid(md.type_())
+            .context("rsa pkey ctx unsupported message digest")?,
+    )
+    .context("rsa pkey ctx set signature md failed")?;
+    if Some(rsa::Padding::PKCS1_PSS).eq(&padding) {
+        ctx.set_rsa_pss_saltlen(sign::RsaPssSaltlen::DIGEST_LENGTH)
+            .context("rsa pkey ctx set pss salt length failed")?;
+    }
+    Ok(())
+}
+
+impl KeyAlgorithmFactory for RsaAesKeyWrapFactory {
+    fn encrypt(
+        &self,
+        pub_key: &[u8],
+        plaintext: &[u8],
+        e: &mut CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        let ephemeral_key = utils::generate_key(EPHEMERAL_KEY_SIZE)?;
+        let wrapped_key =
+            RsaAlgorithmFactory {}.encrypt(pub_key, &ephemeral_key, e)?;
+        Ok([wrapped_key, key_wrap_pad(&ephemeral_key, plaintext)?].concat())
+    }
+
+    fn decrypt(
+        &self,
+        private_key: &[u8],
+        cipher: &[u8],
+        e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        let modulus_size = pkey::PKey::private_key_from_pkcs8(private_key)
+            .context("rsa private key pkcs8 to pkey faield")?
+            .size();
+        if cipher.len() <= modulus_size {
+            return Err(ServiceError::BadRequest(
+                "wrapped key material is truncated".to_owned(),
+            ));
+        }
+        let (wrapped_key, wrapped_material) = cipher.split_at(modulus_size);
+        let ephemeral_key =
+            RsaAlgorithmFactory {}.decrypt(private_key, wrapped_key, e)?;
+        key_unwrap_pad(&ephemeral_key, wrapped_material)
+    }
+
+    fn sign(
+        &self,
+        _pri_key: &[u8],
+        _plaintext: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        Err(ServiceError::Unsupported(
+            "rsa aes key wrap is unsupported sign action".to_owned(),
+        ))
+    }
+
+    fn verify(
+        &self,
+        _pub_key: &[u8],
+        _plaintext: &[u8],
+        _signature: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<bool> {
+        Err(ServiceError::Unsupported(
+            "rsa aes key wrap is unsupported verify action".to_owned(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RsaAesKeyWrapFactory;
+    use crate::crypto::{
+        algorithm::{generate_key, generate_wrapping_key, KeyAlgorithmFactory},
+        types::{KeySpec, WrappingKeyAlgorithm, WrappingKeySpec},
+    };
+
+    #[test]
+    fn test_rsa_aes_key_wrap() {
+        let (private_key, public_key) =
+            generate_wrapping_key(WrappingKeySpec::Rsa2048).unwrap();
+        // rsa-3072 pkcs8 is far beyond a single rsa-oaep block
+        let (material, _) = generate_key(KeySpec::Rsa3072).unwrap();
+        let factory = RsaAesKeyWrapFactory {};
+        let mut adaptor = WrappingKeyAlgorithm::RsaAesKeyWrapSha256.into();
+        let wrapped = factory
+            .encrypt(&public_key, &material, &mut adaptor)
+            .unwrap();
+        assert_eq!(
+            factory.decrypt(&private_key, &wrapped, &adaptor).unwrap(),
+            material
+        );
+        assert!(factory
+            .decrypt(&private_key, &wrapped[.. 256], &adaptor)
+            .is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use itertools::Itertools;
+
+use crate::common::{
+    errors::{Result, ServiceError},
+    utils,
+};
+
+// shamir's secret sharing over GF(2^8), every byte of the secret is shared by
+// its own polynomial, a share is encoded as | x | y0 | y1 | ... |
+
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            // x^8 + x^4 + x^3 + x + 1
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+// a^254 = a^-1 in GF(2^8)
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    for _ in 0 .. 254 {
+        result = gf_mul(result, a);
+    }
+    result
+}
+
+pub fn split(secret: &[u8], shares: u8, threshold: u8) -> Result<Vec<Vec<u8>>> {
+    if threshold == 0 || threshold > shares {
+        return Err(ServiceError::BadRequest(format!(
+            "threshold must be in [1, {}], actual: {}",
+            shares, threshold
+        )));
+    }
+    let mut result = (1 ..= shares).map(|x| vec![x]).collect_vec();
+    for byte in secret {
+        // coefficients of x^1 .. x^(threshold - 1)
+        let coefficients = utils::generate_key(threshold as usize - 1)?;
+        for share in result.iter_mut() {
+            let x = share[0];
+            // horner's method
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0u8, |acc, coefficient| gf_mul(acc, x) ^ coefficient);
+            share.push(gf_mul(y, x) ^ byte);
+        }
+    }
+    Ok(result)
+}
+
+// lagrange interpolation at x = 0
+pub fn combine(shares: &[Vec<u8>]) -> Result<Vec<u8>> {
+    let size = shares.first().map(Vec::len).unwrap_or_default();
+    if size < 2 || shares.iter().any(|share| share.len() != size) {
+        return Err(ServiceError::BadRequest(
+            "shares are malformed".to_owned(),
+        ));
+    }
+    if !shares.iter().map(|share| share[0]).all_unique()
+        || shares.iter().any(|share| share[0] == 0)
+    {
+        return Err(ServiceError::BadRequest(
+            "shares are duplicated or malformed".to_owned(),
+        ));
+    }
+    Ok((1 .. size)
+        .map(|i| {
+            shares.iter().fold(0u8, |secret, share| {
+                let basis = shares
+                    .iter()
+                    .filter(|other| other[0] != share[0])
+                    .fold(1u8, |basis, other| {
+                        gf_mul(
+                            basis,
+                            gf_mul(other[0], gf_inv(other[0] ^ share[0])),
+                        )
+                    });
+                secret ^ gf_mul(share[i], basis)
+            })
+        })
+        .collect_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{combine, split};
+    use crate::common::utils;
+
+    #[test]
+    fn test_split_combine() {
+        let secret = utils::generate_key(32).unwrap();
+        let shares = split(&secret, 5, 3).unwrap();
+        assert_eq!(combine(&shares[.. 3]).unwrap(), secret);
+        assert_eq!(combine(&shares[2 ..]).unwrap(), secret);
+        assert_eq!(
+            combine(&[shares[4].clone(), shares[0].clone(), shares[2].clone()])
+                .unwrap(),
+            secret
+        );
+        // less than threshold reveals nothing useful
+        assert_ne!(combine(&shares[.. 2]).unwrap(), secret);
+        assert!(split(&secret, 3, 4).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use anyhow::{anyhow, Context};
+use openssl::{
+    encrypt, hash,
+    pkey::{self, HasPublic},
+    pkey_ctx,
+};
+
+use super::algorithm::{CryptoAdaptor, KeyAlgorithmFactory};
+use crate::common::errors::{Result, ServiceError};
+
+// GM/T 0009 default distinguishing identifier of the signer
+pub const SM2_DEFAULT_USER_ID: &[u8] = b"1234567812345678";
+const SM2_FIELD_SIZE: usize = 32;
+const SM3_DIGEST_SIZE: usize = 32;
+
+const DER_INTEGER: u8 = 0x02;
+const DER_OCTET_STRING: u8 = 0x04;
+const DER_SEQUENCE: u8 = 0x30;
+
+// sign with sm3 over z || message (GM/T 0003.2), encrypt into the raw
+// C1C3C2 layout (GM/T 0003.4): | 04 | x1 (32) | y1 (32) | sm3 (32) | c2 ... |
+// openssl speaks the asn.1 form of GM/T 0009 which is converted here
+pub struct Sm2AlgorithmFactory {}
+
+impl KeyAlgorithmFactory for Sm2AlgorithmFactory {
+    fn sign(
+        &self,
+        pri_key: &[u8],
+        plaintext: &[u8],
+        e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        let pkey = pkey::PKey::private_key_from_pkcs8(pri_key)
+            .context("import sm2 private key pkcs8 to pkey failed")?;
+        let digest = if e.prehashed {
+            plaintext.to_vec()
+        } else {
+            sm2_digest(&pkey, SM2_DEFAULT_USER_ID, plaintext)?
+        };
+        let mut ctx = pkey_ctx::PkeyCtx::new(&pkey)
+            .context("pkey tansform to sm2 pkey ctx failed")?;
+        ctx.sign_init().context("sm2 pkey ctx sign init failed")?;
+        let mut signature = vec![];
+        ctx.sign_to_vec(&digest, &mut signature)
+            .context("sm2 pkey ctx sign failed")?;
+        Ok(signature)
+    }
+
+    fn verify(
+        &self,
+        pub_key: &[u8],
+        plaintext: &[u8],
+        signature: &[u8],
+        e: &CryptoAdaptor,
+    ) -> Result<bool> {
+        let pkey = pkey::PKey::public_key_from_der(pub_key)
+            .context("import sm2 public key to pkey failed")?;
+        let digest = if e.prehashed {
+            plaintext.to_vec()
+        } else {
+            sm2_digest(&pkey, SM2_DEFAULT_USER_ID, plaintext)?
+        };
+        let mut ctx = pkey_ctx::PkeyCtx::new(&pkey)
+            .context("pkey tansform to sm2 pkey ctx failed")?;
+        ctx.verify_init()
+            .context("sm2 pkey ctx verify init failed")?;
+        // a malformed signature is reported as an error by openssl
+        Ok(ctx.verify(&digest, signature).unwrap_or(false))
+    }
+
+    fn encrypt(
+        &self,
+        pub_key: &[u8],
+        plaintext: &[u8],
+        _e: &mut CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        let pkey = pkey::PKey::public_key_from_der(pub_key)
+            .context("import sm2 public key to pkey failed")?;
+        let encrypter = encrypt::Encrypter::new(&pkey)
+            .context("pkey tansform to sm2 encrypter failed")?;
+        let mut to = vec![
+            0;
+            encrypter
+                .encrypt_len(plaintext)
+                .context("compute sm2 encrypt size failed")?
+        ];
+        let len = encrypter
+            .encrypt(plaintext, &mut to)
+            .context("sm2 encrypter encrypt failed")?;
+        to.truncate(len);
+        der_to_c1c3c2(&to)
+    }
+
+    fn decrypt(
+        &self,
+        private_key: &[u8],
+        cipher: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        let pkey = pkey::PKey::private_key_from_pkcs8(private_key)
+            .context("import sm2 private key pkcs8 to pkey failed")?;
+        let cipher = c1c3c2_to_der(cipher)?;
+        let decrypter = encrypt::Decrypter::new(&pkey)
+            .context("pkey tansform to sm2 decrypter failed")?;
+        let mut to = vec![
+            0;
+            decrypter
+                .decrypt_len(&cipher)
+                .context("compute sm2 decrypt size failed")?
+        ];
+        let len = decrypter
+            .decrypt(&cipher, &mut to)
+            .context("sm2 decrypter decrypt failed")?;
+        to.truncate(len);
+        Ok(to)
+    }
+}
+
+// e = sm3(z || message), z = sm3(entl || id || a || b || xg || yg || xa || ya)
+pub fn sm2_digest<T: HasPublic>(
+    pkey: &pkey::PKeyRef<T>,
+    id: &[u8],
+    message: &[u8],
+) -> Result<Vec<u8>> {
+    let curve = hex::decode(SM2_CURVE_PARAMS).unwrap();
+    let public_point = sm2_public_point(pkey)?;
+    let z = hash::hash(
+        hash::MessageDigest::sm3(),
+        &[
+            &((id.len() * 8) as u16).to_be_bytes(),
+            id,
+            &curve,
+            &public_point[1 ..],
+        ]
+        .concat(),
+    )
+    .context("sm3 z digest failed")?;
+    Ok(
+        hash::hash(hash::MessageDigest::sm3(), &[&z[..], message].concat())
+            .context("sm3 message digest failed")?
+            .to_vec(),
+    )
+}
+
+// a || b || xg || yg of the sm2p256v1 curve (GB/T 32918.5)
+const SM2_CURVE_PARAMS: &str = concat!(
+    "fffffffeffffffffffffffffffffffffffffffff00000000fffffffffffffffc",
+    "28e9fa9e9d9f5e344d5a9e4bcf6509a7f39789f515ab8f92ddbcbd414d940e93",
+    "32c4ae2c1f1981195f9904466a39c9948fe30bbff2660be1715a4589334c74c7",
+    "bc3736a2f4f6779c59bdcee36b692153d0a9877cc62a474002df32e52139f0a0",
+);
+
+// the uncompressed point closes the subject public key info
+fn sm2_public_point<T: HasPublic>(pkey: &pkey::PKeyRef<T>) -> Result<Vec<u8>> {
+    let der = pkey
+        .public_key_to_der()
+        .context("export sm2 public key failed")?;
+    let point_size = 1 + SM2_FIELD_SIZE * 2;
+    match der.len().checked_sub(point_size) {
+        Some(offset) if der[offset] == 0x04 => Ok(der[offset ..].to_vec()),
+        _ => Err(ServiceError::BadRequest(
+            "sm2 public key is not an uncompressed point".to_owned(),
+        )),
+    }
+}
+
+fn der_read(input: &[u8], tag: u8) -> Result<(&[u8], &[u8])> {
+    let malformed =
+        || ServiceError::BadRequest("sm2 ciphertext is malformed".to_owned());
+    if input.len() < 2 || input[0] != tag {
+        return Err(malformed());
+    }
+    let (len, offset) = match input[1] {
+        len @ 0 ..= 0x7f => (len as usize, 2),
+        0x81 if input.len() > 2 => (input[2] as usize, 3),
+        0x82 if input.len() > 3 => {
+            (u16::from_be_bytes([input[2], input[3]]) as usize, 4)
+        }
+        0x83 if input.len() > 4 => (
+            u32::from_be_bytes([0, input[2], input[3], input[4]]) as usize,
+            5,
+        ),
+        _ => return Err(malformed()),
+    };
+    if input.len() < offset + len {
+        return Err(malformed());
+    }
+    Ok((&input[offset .. offset + len], &input[offset + len ..]))
+}
+
+fn der_write(tag: u8, content: &[u8]) -> Vec<u8> {
+    let len = content.len();
+    let header = match len {
+        0 ..= 0x7f => vec![tag, len as u8],
+        0x80 ..= 0xff => vec![tag, 0x81, len as u8],
+        0x100 ..= 0xffff => {
+            [&[tag, 0x82], &(len as u16).to_be_bytes()[..]].concat()
+        }
+        _ => [&[tag, 0x83], &(len as u32).to_be_bytes()[1 ..]].concat(),
+    };
+    [header, content.to_vec()].concat()
+}
+
+fn der_to_c1c3c2(der: &[u8]) -> Result<Vec<u8>> {
+    let (sequence, _) = der_read(der, DER_SEQUENCE)?;
+    let (x, rest) = der_read(sequence, DER_INTEGER)?;
+    let (y, rest) = der_read(rest, DER_INTEGER)?;
+    let (c3, rest) = der_read(rest, DER_OCTET_STRING)?;
+    let (c2, _) = der_read(rest, DER_OCTET_STRING)?;
+    let coordinate = |value: &[u8]| -> Result<Vec<u8>> {
+        let value = &value[value.iter().take_while(|b| **b == 0).count() ..];
+        if value.len() > SM2_FIELD_SIZE {
+            return Err(ServiceError::InternalServer(anyhow!(
+                "sm2 ciphertext coordinate overflows"
+            )));
+        }
+        Ok([vec![0; SM2_FIELD_SIZE - value.len()], value.to_vec()].concat())
+    };
+    Ok([&[0x04], &coordinate(x)?[..], &coordinate(y)?[..], c3, c2].concat())
+}
+
+fn c1c3c2_to_der(raw: &[u8]) -> Result<Vec<u8>> {
+    let c1_size = 1 + SM2_FIELD_SIZE * 2;
+    if raw.len() < c1_size + SM3_DIGEST_SIZE || raw[0] != 0x04 {
+        return Err(ServiceError::BadRequest(
+            "sm2 ciphertext is not C1C3C2 with an uncompressed C1".to_owned(),
+        ));
+    }
+    let integer = |value: &[u8]| {
+        let value = &value[value.iter().take_while(|b| **b == 0).count() ..];
+        match value.first() {
+            None => der_write(DER_INTEGER, &[0]),
+            Some(b) if b & 0x80 != 0 => {
+                der_write(DER_INTEGER, &[&[0], value].concat())
+            }
+            Some(_) => der_write(DER_INTEGER, value),
+        }
+    };
+    let (c1, rest) = raw.split_at(c1_size);
+    let (c3, c2) = rest.split_at(SM3_DIGEST_SIZE);
+    Ok(der_write(
+        DER_SEQUENCE,
+        &[
+            integer(&c1[1 .. 1 + SM2_FIELD_SIZE]),
+            integer(&c1[1 + SM2_FIELD_SIZE ..]),
+            der_write(DER_OCTET_STRING, c3),
+            der_write(DER_OCTET_STRING, c2),
+        ]
+        .concat(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{hash::MessageDigest, pkey, sign};
+
+    use super::{sm2_digest, Sm2AlgorithmFactory, SM2_DEFAULT_USER_ID};
+    use crate::crypto::{
+        algorithm::{generate_key, select_sign_adaptor, KeyAlgorithmFactory},
+        types::{KeyAlgorithm, KeySpec, MessageType},
+    };
+
+    #[test]
+    fn test_sm2_sign_verify() {
+        let factory = Sm2AlgorithmFactory {};
+        let (private_key, public_key) = generate_key(KeySpec::EcSm2).unwrap();
+        let raw = select_sign_adaptor(
+            KeySpec::EcSm2,
+            KeyAlgorithm::SM2DSA,
+            None,
+            MessageType::Raw,
+        )
+        .unwrap();
+        let signature = factory.sign(&private_key, b"message", &raw).unwrap();
+        assert!(factory
+            .verify(&public_key, b"message", &signature, &raw)
+            .unwrap());
+        assert!(!factory
+            .verify(&public_key, b"tampered", &signature, &raw)
+            .unwrap());
+
+        // e = sm3(z || m) signed as a digest is the same signature scheme
+        let digested = select_sign_adaptor(
+            KeySpec::EcSm2,
+            KeyAlgorithm::SM2DSA,
+            None,
+            MessageType::Digest,
+        )
+        .unwrap();
+        let pkey = pkey::PKey::public_key_from_der(&public_key).unwrap();
+        let e = sm2_digest(&pkey, SM2_DEFAULT_USER_ID, b"message").unwrap();
+        assert!(factory
+            .verify(&public_key, &e, &signature, &digested)
+            .unwrap());
+
+        // openssl 3 computes z with an empty user id unless one is set, the
+        // same z computation interoperates with it
+        let private = pkey::PKey::private_key_from_pkcs8(&private_key).unwrap();
+        let mut signer =
+            sign::Signer::new(MessageDigest::sm3(), &private).unwrap();
+        let signature = signer.sign_oneshot_to_vec(b"message").unwrap();
+        let e = sm2_digest(&pkey, b"", b"message").unwrap();
+        assert!(factory
+            .verify(&public_key, &e, &signature, &digested)
+            .unwrap());
+        assert!(!factory
+            .verify(&public_key, b"message", &signature, &raw)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_sm2_encrypt_c1c3c2() {
+        let factory = Sm2AlgorithmFactory {};
+        let (private_key, public_key) = generate_key(KeySpec::EcSm2).unwrap();
+        for plaintext in [&b"p"[..], &[0x5a; 300]] {
+            let cipher = factory
+                .encrypt(
+                    &public_key,
+                    plaintext,
+                    &mut KeyAlgorithm::SM2PKE.into(),
+                )
+                .unwrap();
+            assert_eq!(cipher.len(), 1 + 64 + 32 + plaintext.len());
+            assert_eq!(cipher[0], 0x04);
+            assert_eq!(
+                factory
+                    .decrypt(
+                        &private_key,
+                        &cipher,
+                        &KeyAlgorithm::SM2PKE.into()
+                    )
+                    .unwrap(),
+                plaintext
+            );
+        }
+    }
+}
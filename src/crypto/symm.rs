@@ -1,8 +1,14 @@
 use anyhow::Context;
-use openssl::symm::{self};
+use openssl::{
+    cipher, cipher_ctx,
+    symm::{self},
+};
 
 use super::{
-    algorithm::{select_cipher, CryptoAdaptor, KeyAlgorithmFactory},
+    algorithm::{
+        select_cipher, select_fetched_cipher, CryptoAdaptor, EncryptKits,
+        KeyAlgorithmFactory, XCHACHA20_NONCE_SIZE,
+    },
     types::KeyAlgorithm,
 };
 use crate::common::{
@@ -18,6 +24,11 @@ pub struct AEADAlgorithmFactory {
     alg: KeyAlgorithm,
 }
 
+// aes-siv (rfc 5297) and aes-gcm-siv (rfc 8452), the synthetic iv is the tag
+pub struct SivAlgorithmFactory {
+    alg: KeyAlgorithm,
+}
+
 impl CipherAlgorithmFactory {
     pub fn new(alg: KeyAlgorithm) -> Self {
         Self { alg }
@@ -28,12 +39,275 @@ impl AEADAlgorithmFactory {
     pub fn new(alg: KeyAlgorithm) -> Self {
         Self { alg }
     }
+
+    // key and nonce actually fed to the cipher, xchacha20 derives a subkey
+    // from the first 16 bytes of the nonce and keeps the last 8
+    fn cipher_params(
+        &self,
+        key: &[u8],
+        iv: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>)> {
+        if !KeyAlgorithm::XChaCha20Poly1305.eq(&self.alg) {
+            return Ok((key.to_vec(), iv.to_vec()));
+        }
+        if XCHACHA20_NONCE_SIZE != iv.len() {
+            return Err(ServiceError::BadRequest(format!(
+                "xchacha20 nonce length is invalid: {}",
+                iv.len()
+            )));
+        }
+        Ok((hchacha20(key, &iv[.. 16])?, [&[0; 4], &iv[16 ..]].concat()))
+    }
+}
+
+impl SivAlgorithmFactory {
+    pub fn new(alg: KeyAlgorithm) -> Self {
+        Self { alg }
+    }
+
+    fn crypt(
+        &self,
+        key: &[u8],
+        input: &[u8],
+        kits: &mut EncryptKits,
+        encrypt: bool,
+    ) -> Result<Vec<u8>> {
+        match (self.alg, encrypt) {
+            (KeyAlgorithm::AesGcmSIV, true) => {
+                let (ciphertext, tag) = fetched_aead_seal(
+                    select_fetched_cipher(key.len(), self.alg)?,
+                    key,
+                    &kits.iv,
+                    &kits.aad,
+                    input,
+                    16,
+                )?;
+                kits.tag = tag;
+                Ok(ciphertext)
+            }
+            (KeyAlgorithm::AesGcmSIV, false) => fetched_aead_open(
+                select_fetched_cipher(key.len(), self.alg)?,
+                key,
+                &kits.iv,
+                &kits.aad,
+                input,
+                &kits.tag,
+            ),
+            _ => aes_siv_crypt(key, input, kits, encrypt),
+        }
+    }
+}
+
+// aes-siv splits its key in two halves, a 256 bits key is aes-128-siv, the
+// nonce is taken as the last associated data component
+fn aes_siv_crypt(
+    key: &[u8],
+    input: &[u8],
+    kits: &mut EncryptKits,
+    encrypt: bool,
+) -> Result<Vec<u8>> {
+    let name = match key.len() * 8 {
+        256 => "AES-128-SIV",
+        512 => "AES-256-SIV",
+        _ => {
+            return Err(ServiceError::Unsupported(format!(
+                "unsupported aes siv key length: {}",
+                key.len()
+            )))
+        }
+    };
+    let cipher = cipher::Cipher::fetch(None, name, None)
+        .context(format!("fetch cipher {} failed", name))?;
+    let mut ctx =
+        cipher_ctx::CipherCtx::new().context("aes siv initialize failed")?;
+    if encrypt {
+        ctx.encrypt_init(Some(&cipher), Some(key), None)
+    } else {
+        ctx.decrypt_init(Some(&cipher), Some(key), None)
+    }
+    .context("aes siv initialize failed")?;
+    if !encrypt {
+        ctx.set_tag(&kits.tag).context("aes siv set tag failed")?;
+    }
+    for component in [&kits.aad, &kits.iv] {
+        if !component.is_empty() {
+            ctx.cipher_update(component, None)
+                .context("aes siv update associated data failed")?;
+        }
+    }
+    let mut output = vec![0; input.len() + cipher.block_size()];
+    let mut count = ctx
+        .cipher_update(input, Some(&mut output))
+        .context("aes siv crypt failed")?;
+    count += ctx
+        .cipher_final(&mut output[count ..])
+        .context("aes siv crypt failed")?;
+    output.truncate(count);
+    if encrypt {
+        ctx.tag(&mut kits.tag).context("aes siv get tag failed")?;
+    }
+    Ok(output)
+}
+
+pub fn fetched_aead_seal(
+    name: &str,
+    key: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+    tag_size: usize,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut tag = vec![0; tag_size];
+    let ciphertext =
+        fetched_aead_crypt(name, key, iv, aad, plaintext, &mut tag, true)?;
+    Ok((ciphertext, tag))
+}
+
+pub fn fetched_aead_open(
+    name: &str,
+    key: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+    tag: &[u8],
+) -> Result<Vec<u8>> {
+    let mut tag = tag.to_vec();
+    fetched_aead_crypt(name, key, iv, aad, ciphertext, &mut tag, false)
+}
+
+// aead ciphers only reachable by name, the input goes in a single update as
+// gcm-siv and ccm require, ccm also needs the tag and data length upfront
+fn fetched_aead_crypt(
+    name: &str,
+    key: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    input: &[u8],
+    tag: &mut [u8],
+    encrypt: bool,
+) -> Result<Vec<u8>> {
+    let ccm = name.ends_with("-CCM");
+    // a provider without the cipher leaves the algorithm unsupported
+    let cipher = cipher::Cipher::fetch(None, name, None).map_err(|_| {
+        ServiceError::Unsupported(format!(
+            "cipher {} is not offered by the openssl provider",
+            name
+        ))
+    })?;
+    let mut ctx = cipher_ctx::CipherCtx::new()
+        .context(format!("{} initialize failed", name))?;
+    if encrypt {
+        ctx.encrypt_init(Some(&cipher), None, None)
+    } else {
+        ctx.decrypt_init(Some(&cipher), None, None)
+    }
+    .context(format!("{} initialize failed", name))?;
+    // the cipher reports 12 bytes for ccm whereas the context defaults to 7
+    if iv.len() != ctx.iv_length() {
+        ctx.set_iv_length(iv.len())
+            .context(format!("{} set iv length failed", name))?;
+    }
+    if ccm && encrypt {
+        ctx.set_tag_length(tag.len())
+            .context(format!("{} set tag length failed", name))?;
+    }
+    if !encrypt {
+        ctx.set_tag(tag)
+            .context(format!("{} set tag failed", name))?;
+    }
+    if encrypt {
+        ctx.encrypt_init(None, Some(key), Some(iv))
+    } else {
+        ctx.decrypt_init(None, Some(key), Some(iv))
+    }
+    .context(format!("{} initialize failed", name))?;
+    if ccm {
+        ctx.set_data_len(input.len())
+            .context(format!("{} set data length failed", name))?;
+    }
+    if !aad.is_empty() {
+        ctx.cipher_update(aad, None)
+            .context(format!("{} update associated data failed", name))?;
+    }
+    let mut output = vec![0; input.len() + cipher.block_size()];
+    let mut count = ctx
+        .cipher_update(input, Some(&mut output))
+        .context(format!("{} crypt failed", name))?;
+    count += ctx
+        .cipher_final(&mut output[count ..])
+        .context(format!("{} crypt failed", name))?;
+    output.truncate(count);
+    if encrypt {
+        ctx.tag(tag).context(format!("{} get tag failed", name))?;
+    }
+    Ok(output)
+}
+
+// draft-irtf-cfrg-xchacha, a chacha20 keystream block is the permuted state
+// plus the input state, subtracting the input rows yields hchacha20
+pub fn hchacha20(key: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+    const SIGMA: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+    let block =
+        symm::encrypt(symm::Cipher::chacha20(), key, Some(nonce), &[0; 64])
+            .context("hchacha20 keystream failed")?;
+    let word = |bytes: &[u8], i: usize| {
+        u32::from_le_bytes(bytes[i * 4 .. i * 4 + 4].try_into().unwrap())
+    };
+    Ok((0 .. 4)
+        .map(|i| word(&block, i).wrapping_sub(SIGMA[i]))
+        .chain(
+            (0 .. 4).map(|i| word(&block, 12 + i).wrapping_sub(word(nonce, i))),
+        )
+        .flat_map(u32::to_le_bytes)
+        .collect())
 }
 
 pub fn generate_iv(size: usize) -> Result<Vec<u8>> {
     utils::generate_key(size)
 }
 
+// rfc 5649 aes key wrap with padding, the default alternative iv is used
+pub fn key_wrap_pad(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    key_wrap_crypt(key, plaintext, true)
+}
+
+pub fn key_unwrap_pad(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    key_wrap_crypt(key, ciphertext, false)
+}
+
+fn key_wrap_crypt(key: &[u8], input: &[u8], encrypt: bool) -> Result<Vec<u8>> {
+    let cipher = match key.len() * 8 {
+        128 => cipher::Cipher::aes_128_wrap_pad(),
+        192 => cipher::Cipher::aes_192_wrap_pad(),
+        256 => cipher::Cipher::aes_256_wrap_pad(),
+        _ => {
+            return Err(ServiceError::BadRequest(format!(
+                "aes key wrap key length is invalid: {}",
+                key.len()
+            )))
+        }
+    };
+    let mut ctx = cipher_ctx::CipherCtx::new()
+        .context("aes key wrap initialize failed")?;
+    ctx.set_flags(cipher_ctx::CipherCtxFlags::FLAG_WRAP_ALLOW);
+    if encrypt {
+        ctx.encrypt_init(Some(cipher), Some(key), None)
+    } else {
+        ctx.decrypt_init(Some(cipher), Some(key), None)
+    }
+    .context("aes key wrap initialize failed")?;
+    // padding and integrity check block, cipher_final requires a spare block
+    let mut output = vec![0; input.len() + cipher.block_size() * 4];
+    let mut count = ctx
+        .cipher_update(input, Some(&mut output))
+        .context("aes key wrap failed")?;
+    count += ctx
+        .cipher_final(&mut output[count ..])
+        .context("aes key wrap failed")?;
+    output.truncate(count);
+    Ok(output)
+}
+
 impl KeyAlgorithmFactory for AEADAlgorithmFactory {
     fn encrypt(
         &self,
@@ -41,12 +315,25 @@ impl KeyAlgorithmFactory for AEADAlgorithmFactory {
         plaintext: &[u8],
         e: &mut CryptoAdaptor,
     ) -> Result<Vec<u8>> {
-        let ccipher = select_cipher(key.len(), self.alg)?;
         let kits = e.kits.as_mut().unwrap();
+        if let KeyAlgorithm::Sm4GCM | KeyAlgorithm::Sm4CCM = self.alg {
+            let (ciphertext, tag) = fetched_aead_seal(
+                select_fetched_cipher(key.len(), self.alg)?,
+                key,
+                &kits.iv,
+                &kits.aad,
+                plaintext,
+                kits.tag.len(),
+            )?;
+            kits.tag = tag;
+            return Ok(ciphertext);
+        }
+        let ccipher = select_cipher(key.len(), self.alg)?;
+        let (key, iv) = self.cipher_params(key, &kits.iv)?;
         Ok(symm::encrypt_aead(
             ccipher,
-            key,
-            Some(&kits.iv),
+            &key,
+            Some(&iv),
             &kits.aad,
             plaintext,
             &mut kits.tag,
@@ -60,13 +347,24 @@ impl KeyAlgorithmFactory for AEADAlgorithmFactory {
         cipher: &[u8],
         e: &CryptoAdaptor,
     ) -> Result<Vec<u8>> {
-        let ccipher = select_cipher(key.len(), self.alg)?;
         let kits = e.kits.as_ref().unwrap();
+        if let KeyAlgorithm::Sm4GCM | KeyAlgorithm::Sm4CCM = self.alg {
+            return fetched_aead_open(
+                select_fetched_cipher(key.len(), self.alg)?,
+                key,
+                &kits.iv,
+                &kits.aad,
+                cipher,
+                &kits.tag,
+            );
+        }
+        let ccipher = select_cipher(key.len(), self.alg)?;
+        let (key, iv) = self.cipher_params(key, &kits.iv)?;
 
         Ok(symm::decrypt_aead(
             ccipher,
-            key,
-            Some(&kits.iv),
+            &key,
+            Some(&iv),
             &kits.aad,
             cipher,
             &kits.tag,
@@ -98,6 +396,49 @@ impl KeyAlgorithmFactory for AEADAlgorithmFactory {
     }
 }
 
+impl KeyAlgorithmFactory for SivAlgorithmFactory {
+    fn encrypt(
+        &self,
+        key: &[u8],
+        plaintext: &[u8],
+        e: &mut CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        self.crypt(key, plaintext, e.kits.as_mut().unwrap(), true)
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8],
+        cipher: &[u8],
+        e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        self.crypt(key, cipher, &mut e.kits.clone().unwrap(), false)
+    }
+
+    fn sign(
+        &self,
+        _pri_key: &[u8],
+        _plaintext: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<Vec<u8>> {
+        Err(ServiceError::Unsupported(
+            "siv is unsupported sign action".to_owned(),
+        ))
+    }
+
+    fn verify(
+        &self,
+        _pub_key: &[u8],
+        _plaintext: &[u8],
+        _signature: &[u8],
+        _e: &CryptoAdaptor,
+    ) -> Result<bool> {
+        Err(ServiceError::Unsupported(
+            "siv is unsupported verify action".to_owned(),
+        ))
+    }
+}
+
 impl KeyAlgorithmFactory for CipherAlgorithmFactory {
     fn sign(
         &self,
@@ -157,13 +498,19 @@ impl KeyAlgorithmFactory for CipherAlgorithmFactory {
 
 #[cfg(test)]
 mod tests {
+    use openssl::cipher;
 
-    use super::{generate_iv, CipherAlgorithmFactory};
+    use super::{
+        fetched_aead_open, fetched_aead_seal, generate_iv, hchacha20,
+        key_unwrap_pad, key_wrap_pad, CipherAlgorithmFactory,
+        SivAlgorithmFactory,
+    };
     use crate::{
-        common::utils,
+        common::{errors::ServiceError, utils},
         crypto::{
             algorithm::{
-                generate_key, CryptoAdaptor, EncryptKits, KeyAlgorithmFactory,
+                generate_key, select_encrypt_adaptor, select_fetched_cipher,
+                CryptoAdaptor, EncryptKits, KeyAlgorithmFactory,
             },
             symm::AEADAlgorithmFactory,
             types::{KeyAlgorithm, KeySpec},
@@ -226,4 +573,291 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_sm4_aead_vector() {
+        // rfc 8998 appendix a.1 and a.2
+        let key = hex::decode("0123456789abcdeffedcba9876543210").unwrap();
+        let nonce = hex::decode("00001234567800000000abcd").unwrap();
+        let aad =
+            hex::decode("feedfacedeadbeeffeedfacedeadbeefabaddad2").unwrap();
+        let plaintext = hex::decode(
+            "aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbccccccccccccccccdddddddddddddddd\
+             eeeeeeeeeeeeeeeeffffffffffffffffeeeeeeeeeeeeeeeeaaaaaaaaaaaaaaaa",
+        )
+        .unwrap();
+        for (alg, ciphertext, tag) in [
+            (
+                KeyAlgorithm::Sm4GCM,
+                "17f399f08c67d5ee19d0dc9969c4bb7d5fd46fd3756489069157b282bb200735\
+                 d82710ca5c22f0ccfa7cbf93d496ac15a56834cbcf98c397b4024a2691233b8d",
+                "83de3541e4c2b58177e065a9bf7b62ec",
+            ),
+            (
+                KeyAlgorithm::Sm4CCM,
+                "48af93501fa62adbcd414cce6034d895dda1bf8f132f042098661572e7483094\
+                 fd12e518ce062c98acee28d95df4416bed31a2f04476c18bb40c84a74b97dc5b",
+                "16842d4fa186f56ab33256971fa110f4",
+            ),
+        ] {
+            let name = select_fetched_cipher(key.len(), alg).unwrap();
+            let (sealed, sealed_tag) = match fetched_aead_seal(
+                name, &key, &nonce, &aad, &plaintext, 16,
+            ) {
+                // the provider of the linked openssl may not offer it
+                Err(ServiceError::Unsupported(_)) => continue,
+                sealed => sealed.unwrap(),
+            };
+            assert_eq!(hex::encode(&sealed), ciphertext);
+            assert_eq!(hex::encode(&sealed_tag), tag);
+            assert_eq!(
+                fetched_aead_open(
+                    name,
+                    &key,
+                    &nonce,
+                    &aad,
+                    &sealed,
+                    &sealed_tag
+                )
+                .unwrap(),
+                plaintext
+            );
+            assert!(fetched_aead_open(
+                name,
+                &key,
+                &nonce,
+                b"tampered",
+                &sealed,
+                &sealed_tag
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_chacha20_poly1305() {
+        let (key, _nil) = generate_key(KeySpec::Aes256).unwrap();
+        for (alg, iv_len) in [
+            (KeyAlgorithm::ChaCha20Poly1305, 12),
+            (KeyAlgorithm::XChaCha20Poly1305, 24),
+        ] {
+            let factory = AEADAlgorithmFactory::new(alg);
+            let mut crypto = CryptoAdaptor {
+                kits: Some(EncryptKits {
+                    iv: generate_iv(iv_len).unwrap(),
+                    aad: b"aad".to_vec(),
+                    tag: vec![0; 16],
+                }),
+                ..Default::default()
+            };
+            let cipher =
+                factory.encrypt(&key, b"plaintext", &mut crypto).unwrap();
+            assert_eq!(
+                factory.decrypt(&key, &cipher, &crypto).unwrap(),
+                b"plaintext",
+            );
+            crypto.kits.as_mut().unwrap().aad = b"tampered".to_vec();
+            assert!(factory.decrypt(&key, &cipher, &crypto).is_err());
+        }
+    }
+
+    #[test]
+    fn test_siv() {
+        for (spec, alg) in [
+            (KeySpec::Aes256, KeyAlgorithm::AesSIV),
+            (KeySpec::Aes128, KeyAlgorithm::AesGcmSIV),
+            (KeySpec::Aes256, KeyAlgorithm::AesGcmSIV),
+        ] {
+            let factory = SivAlgorithmFactory::new(alg);
+            let (key, _nil) = generate_key(spec).unwrap();
+            // the provider of the linked openssl may not offer aes-gcm-siv
+            if KeyAlgorithm::AesGcmSIV.eq(&alg)
+                && cipher::Cipher::fetch(
+                    None,
+                    select_fetched_cipher(key.len(), alg).unwrap(),
+                    None,
+                )
+                .is_err()
+            {
+                continue;
+            }
+            for deterministic in [true, false] {
+                let encrypt = || {
+                    let mut crypto = select_encrypt_adaptor(
+                        alg,
+                        &key,
+                        b"aad".to_vec(),
+                        deterministic,
+                    )
+                    .unwrap();
+                    let cipher = factory
+                        .encrypt(&key, b"plaintext", &mut crypto)
+                        .unwrap();
+                    (cipher, crypto)
+                };
+                let (cipher, mut crypto) = encrypt();
+                assert_eq!(
+                    factory.decrypt(&key, &cipher, &crypto).unwrap(),
+                    b"plaintext",
+                );
+                assert_eq!(deterministic, cipher == encrypt().0);
+                crypto.kits.as_mut().unwrap().aad = b"tampered".to_vec();
+                assert!(factory.decrypt(&key, &cipher, &crypto).is_err());
+            }
+        }
+        assert!(select_encrypt_adaptor(
+            KeyAlgorithm::AesGCM,
+            &[0; 32],
+            vec![],
+            true
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_aes_siv_vector() {
+        // rfc 5297 appendix a.1, deterministic authenticated encryption
+        let key = hex::decode(
+            "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff",
+        )
+        .unwrap();
+        let mut crypto = CryptoAdaptor {
+            kits: Some(EncryptKits {
+                iv: vec![],
+                aad: hex::decode(
+                    "101112131415161718191a1b1c1d1e1f2021222324252627",
+                )
+                .unwrap(),
+                tag: vec![0; 16],
+            }),
+            ..Default::default()
+        };
+        let cipher = SivAlgorithmFactory::new(KeyAlgorithm::AesSIV)
+            .encrypt(
+                &key,
+                &hex::decode("112233445566778899aabbccddee").unwrap(),
+                &mut crypto,
+            )
+            .unwrap();
+        assert_eq!(
+            hex::encode(crypto.kits.unwrap().tag),
+            "85632d07c6e8f37f950acd320a2ecc93"
+        );
+        assert_eq!(hex::encode(cipher), "40c02b9690c4dc04daef7f6afe5c");
+    }
+
+    #[test]
+    fn test_aes_gcm_siv_vector() {
+        // rfc 8452 appendix c.1 and c.2
+        let nonce = hex::decode("030000000000000000000000").unwrap();
+        let key128 = "01000000000000000000000000000000";
+        let key256 =
+            "0100000000000000000000000000000000000000000000000000000000000000";
+        for (key, aad, plaintext, ciphertext, tag) in [
+            (key128, "", "", "", "dc20e2d83f25705bb49e439eca56de25"),
+            (
+                key128,
+                "",
+                "0100000000000000",
+                "b5d839330ac7b786",
+                "578782fff6013b815b287c22493a364c",
+            ),
+            (
+                key128,
+                "01",
+                "0200000000000000",
+                "1e6daba35669f427",
+                "3b0a1a2560969cdf790d99759abd1508",
+            ),
+            (
+                key128,
+                "01",
+                "0200000000000000000000000000000003000000000000000000000000000000",
+                "620048ef3c1e73e57e02bb8562c416a319e73e4caac8e96a1ecb2933145a1d71",
+                "e6af6a7f87287da059a71684ed3498e1",
+            ),
+            (
+                key128,
+                "010000000000000000000000",
+                "02000000000000000000000000000000030000000000000000000000000000000400000000000000",
+                "124d899ad6b9037a368f093a11959b92231731f451b5a10427256f32c9baf2b6b21f22eeb74e75ae",
+                "7d2f9b228a7604d2eac4d36e07148cee",
+            ),
+            (key256, "", "", "", "07f5f4169bbf55a8400cd47ea6fd400f"),
+            (
+                key256,
+                "01",
+                "0200000000000000000000000000000003000000000000000000000000000000",
+                "07dad364bfc2b9da89116d7bef6daaaf6f255510aa654f920ac81b94e8bad365",
+                "aea1bad12702e1965604374aab96dbbc",
+            ),
+        ] {
+            let key = hex::decode(key).unwrap();
+            let aad = hex::decode(aad).unwrap();
+            let plaintext = hex::decode(plaintext).unwrap();
+            let name =
+                select_fetched_cipher(key.len(), KeyAlgorithm::AesGcmSIV)
+                    .unwrap();
+            let (sealed, sealed_tag) = match fetched_aead_seal(
+                name, &key, &nonce, &aad, &plaintext, 16,
+            ) {
+                // the provider of the linked openssl may not offer it
+                Err(ServiceError::Unsupported(_)) => return,
+                sealed => sealed.unwrap(),
+            };
+            assert_eq!(hex::encode(&sealed), ciphertext);
+            assert_eq!(hex::encode(&sealed_tag), tag);
+            assert_eq!(
+                fetched_aead_open(name, &key, &nonce, &aad, &sealed, &sealed_tag)
+                    .unwrap(),
+                plaintext
+            );
+            // the tag binds the associated data
+            assert!(fetched_aead_open(
+                name,
+                &key,
+                &nonce,
+                b"another aad",
+                &sealed,
+                &sealed_tag
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_hchacha20() {
+        // draft-irtf-cfrg-xchacha section 2.2.1
+        let key = (0u8 .. 32).collect::<Vec<u8>>();
+        let nonce = hex::decode("000000090000004a0000000031415927").unwrap();
+        assert_eq!(
+            hex::encode(hchacha20(&key, &nonce).unwrap()),
+            "82413b4227b27bfed30e42508a877d73a0f9e4d58a74a853c12ec41326d3ecdc"
+        );
+    }
+
+    #[test]
+    fn test_key_wrap_pad() {
+        let kek = utils::generate_key(32).unwrap();
+        // rfc 5649 section 6, 20 octets key with 192 bits kek
+        let rfc_kek =
+            hex::decode("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8")
+                .unwrap();
+        assert_eq!(
+            hex::encode(
+                key_wrap_pad(
+                    &rfc_kek,
+                    &hex::decode("c37b7e6492584340bed12207808941155068f738")
+                        .unwrap()
+                )
+                .unwrap()
+            ),
+            "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a"
+        );
+        for size in [1, 7, 8, 20, 1217] {
+            let material = utils::generate_key(size).unwrap();
+            let wrapped = key_wrap_pad(&kek, &material).unwrap();
+            assert_eq!(key_unwrap_pad(&kek, &wrapped).unwrap(), material);
+        }
+    }
 }
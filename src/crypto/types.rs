@@ -1,43 +1,135 @@
 use std::{self, fmt::Display, option::Option};
 
-use openssl::{cipher::Cipher, nid::Nid};
-use openssl_sys::NID_sm4_cbc;
+use openssl::{cipher::Cipher, hash, nid::Nid};
+use openssl_sys::{NID_sm4_cbc, NID_ED25519, NID_ED448, NID_X25519, NID_X448};
 use sea_orm::{DeriveActiveEnum, EnumIter};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::common::errors::{Result, ServiceError};
 
-#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Debug, Copy)]
+// the discriminant is persisted in ciphertext blobs, never reorder it
+#[derive(
+    Deserialize, Serialize, Clone, PartialEq, Eq, Debug, Copy, ToSchema,
+)]
+#[repr(u8)]
 pub enum KeyAlgorithm {
     // encrypt and decrypt
     #[serde(rename = "AES_CBC")]
-    AesCBC,
+    AesCBC = 1,
     #[serde(rename = "AES_GCM")]
-    AesGCM,
+    AesGCM = 2,
     #[serde(rename = "RSAES_OAEP")]
-    RsaOAEP,
+    RsaOAEP = 3,
     #[serde(rename = "SM2PKE")]
-    SM2PKE,
+    SM2PKE = 4,
     #[serde(rename = "SM4_CTR")]
-    Sm4CTR,
+    Sm4CTR = 5,
     #[serde(rename = "SM4_CBC")]
-    Sm4CBC,
+    Sm4CBC = 6,
     #[serde(rename = "ECIES_DH_SHA_1_XOR_HMAC")]
-    EciesSha1,
+    EciesSha1 = 7,
 
     // sign and verify
     #[serde(rename = "RSA_PSS")]
-    RsaPSS,
+    RsaPSS = 8,
     #[serde(rename = "RSA_PKCS1")]
-    RsaPKCS1,
+    RsaPKCS1 = 9,
     #[serde(rename = "ECDSA")]
-    Ecdsa,
+    Ecdsa = 10,
     #[serde(rename = "SM2DSA")]
-    SM2DSA,
+    SM2DSA = 11,
+    #[serde(rename = "EDDSA")]
+    EdDSA = 12,
+    #[serde(rename = "HMAC")]
+    Hmac = 13,
+    #[serde(rename = "CHACHA20_POLY1305")]
+    ChaCha20Poly1305 = 14,
+    #[serde(rename = "XCHACHA20_POLY1305")]
+    XChaCha20Poly1305 = 15,
+    #[serde(rename = "AES_SIV")]
+    AesSIV = 16,
+    #[serde(rename = "AES_GCM_SIV")]
+    AesGcmSIV = 17,
+    #[serde(rename = "SM4_GCM")]
+    Sm4GCM = 18,
+    #[serde(rename = "SM4_CCM")]
+    Sm4CCM = 19,
+    #[serde(rename = "ECIES_HKDF_SHA_256_AES_GCM")]
+    EciesHkdfAesGCM = 20,
+
+    // key agreement
+    #[serde(rename = "ECDH")]
+    Ecdh = 21,
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+impl KeyAlgorithm {
+    pub fn usage(&self) -> KeyUsage {
+        match self {
+            KeyAlgorithm::AesCBC
+            | KeyAlgorithm::AesGCM
+            | KeyAlgorithm::RsaOAEP
+            | KeyAlgorithm::SM2PKE
+            | KeyAlgorithm::Sm4CTR
+            | KeyAlgorithm::Sm4CBC
+            | KeyAlgorithm::EciesSha1
+            | KeyAlgorithm::ChaCha20Poly1305
+            | KeyAlgorithm::XChaCha20Poly1305
+            | KeyAlgorithm::AesSIV
+            | KeyAlgorithm::AesGcmSIV
+            | KeyAlgorithm::Sm4GCM
+            | KeyAlgorithm::Sm4CCM
+            | KeyAlgorithm::EciesHkdfAesGCM => KeyUsage::EncryptAndDecrypt,
+            KeyAlgorithm::RsaPSS
+            | KeyAlgorithm::RsaPKCS1
+            | KeyAlgorithm::Ecdsa
+            | KeyAlgorithm::SM2DSA
+            | KeyAlgorithm::EdDSA => KeyUsage::SignAndVerify,
+            KeyAlgorithm::Hmac => KeyUsage::GenerateAndVerifyMac,
+            KeyAlgorithm::Ecdh => KeyUsage::KeyAgreement,
+        }
+    }
+}
+
+impl TryFrom<u8> for KeyAlgorithm {
+    type Error = ServiceError;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Ok(match value {
+            1 => KeyAlgorithm::AesCBC,
+            2 => KeyAlgorithm::AesGCM,
+            3 => KeyAlgorithm::RsaOAEP,
+            4 => KeyAlgorithm::SM2PKE,
+            5 => KeyAlgorithm::Sm4CTR,
+            6 => KeyAlgorithm::Sm4CBC,
+            7 => KeyAlgorithm::EciesSha1,
+            8 => KeyAlgorithm::RsaPSS,
+            9 => KeyAlgorithm::RsaPKCS1,
+            10 => KeyAlgorithm::Ecdsa,
+            11 => KeyAlgorithm::SM2DSA,
+            12 => KeyAlgorithm::EdDSA,
+            13 => KeyAlgorithm::Hmac,
+            14 => KeyAlgorithm::ChaCha20Poly1305,
+            15 => KeyAlgorithm::XChaCha20Poly1305,
+            16 => KeyAlgorithm::AesSIV,
+            17 => KeyAlgorithm::AesGcmSIV,
+            18 => KeyAlgorithm::Sm4GCM,
+            19 => KeyAlgorithm::Sm4CCM,
+            20 => KeyAlgorithm::EciesHkdfAesGCM,
+            21 => KeyAlgorithm::Ecdh,
+            _ => {
+                return Err(ServiceError::BadRequest(format!(
+                    "unknown key algorithm: {}",
+                    value
+                )))
+            }
+        })
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema,
+)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum MessageDigest {
     Sha1,
@@ -49,13 +141,79 @@ pub enum MessageDigest {
     Sha3_256,
     Sha3_384,
     Sha3_512,
+    Sm3,
+}
+
+impl From<MessageDigest> for hash::MessageDigest {
+    fn from(value: MessageDigest) -> Self {
+        match value {
+            MessageDigest::Sha1 => hash::MessageDigest::sha1(),
+            MessageDigest::Sha224 => hash::MessageDigest::sha224(),
+            MessageDigest::Sha256 => hash::MessageDigest::sha256(),
+            MessageDigest::Sha384 => hash::MessageDigest::sha384(),
+            MessageDigest::Sha512 => hash::MessageDigest::sha512(),
+            MessageDigest::Sha3_224 => hash::MessageDigest::sha3_224(),
+            MessageDigest::Sha3_256 => hash::MessageDigest::sha3_256(),
+            MessageDigest::Sha3_384 => hash::MessageDigest::sha3_384(),
+            MessageDigest::Sha3_512 => hash::MessageDigest::sha3_512(),
+            MessageDigest::Sm3 => hash::MessageDigest::sm3(),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+// the message to be signed is the raw message or the digest of it
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MessageType {
+    #[default]
+    Raw,
+    Digest,
+}
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema,
+)]
+pub enum PublicKeyFormat {
+    #[serde(rename = "DER")]
+    Der,
+    #[default]
+    #[serde(rename = "PEM")]
+    Pem,
+    #[serde(rename = "JWK")]
+    Jwk,
+    #[serde(rename = "OPENSSH")]
+    OpenSsh,
+}
+
+// x509 key usage extension bits (rfc 5280 4.2.1.3)
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CertificateKeyUsage {
+    DigitalSignature,
+    NonRepudiation,
+    KeyEncipherment,
+    DataEncipherment,
+    KeyAgreement,
+    KeyCertSign,
+    CrlSign,
+}
+
+// x509 extended key usage purposes (rfc 5280 4.2.1.12)
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema,
+)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-pub enum Padding {
-    Pkcs7Padding,
-    NoPadding,
+pub enum ExtendedKeyUsage {
+    ServerAuth,
+    ClientAuth,
+    CodeSigning,
+    EmailProtection,
+    TimeStamping,
+    OcspSigning,
 }
 
 #[derive(
@@ -81,6 +239,12 @@ pub enum KeyUsage {
     #[sea_orm(string_value = "SIGN/VERIFY")]
     #[serde(rename = "SIGN/VERIFY")]
     SignAndVerify,
+    #[sea_orm(string_value = "GENERATE/VERIFY_MAC")]
+    #[serde(rename = "GENERATE/VERIFY_MAC")]
+    GenerateAndVerifyMac,
+    #[sea_orm(string_value = "KEY_AGREEMENT")]
+    #[serde(rename = "KEY_AGREEMENT")]
+    KeyAgreement,
 }
 
 #[derive(
@@ -159,15 +323,51 @@ pub enum KeySpec {
     #[sea_orm(string_value = "RSA_3072")]
     #[serde(rename = "RSA_3072")]
     Rsa3072,
+    #[sea_orm(string_value = "RSA_4096")]
+    #[serde(rename = "RSA_4096")]
+    Rsa4096,
     #[sea_orm(string_value = "EC_P256")]
     #[serde(rename = "EC_P256")]
     EcP256,
     #[sea_orm(string_value = "EC_P256k")]
     #[serde(rename = "EC_P256K")]
     EcP256K,
+    #[sea_orm(string_value = "EC_P384")]
+    #[serde(rename = "EC_P384")]
+    EcP384,
+    #[sea_orm(string_value = "EC_P521")]
+    #[serde(rename = "EC_P521")]
+    EcP521,
+    #[sea_orm(string_value = "EC_SM2")]
+    #[serde(rename = "EC_SM2")]
+    EcSm2,
     #[sea_orm(string_value = "SM4")]
     #[serde(rename = "SM4")]
     SM4,
+    #[sea_orm(string_value = "ED25519")]
+    #[serde(rename = "ED25519")]
+    Ed25519,
+    #[sea_orm(string_value = "ED448")]
+    #[serde(rename = "ED448")]
+    Ed448,
+    #[sea_orm(string_value = "X25519")]
+    #[serde(rename = "X25519")]
+    X25519,
+    #[sea_orm(string_value = "X448")]
+    #[serde(rename = "X448")]
+    X448,
+    #[sea_orm(string_value = "HMAC_SHA256")]
+    #[serde(rename = "HMAC_SHA256")]
+    HmacSha256,
+    #[sea_orm(string_value = "HMAC_SHA384")]
+    #[serde(rename = "HMAC_SHA384")]
+    HmacSha384,
+    #[sea_orm(string_value = "HMAC_SHA512")]
+    #[serde(rename = "HMAC_SHA512")]
+    HmacSha512,
+    #[sea_orm(string_value = "HMAC_SM3")]
+    #[serde(rename = "HMAC_SM3")]
+    HmacSm3,
 }
 
 impl From<KeySpec> for (Nid, usize) {
@@ -181,11 +381,24 @@ impl From<KeySpec> for (Nid, usize) {
             }
             KeySpec::Rsa2048 => (Nid::RSA, 256),
             KeySpec::Rsa3072 => (Nid::RSA, 384),
+            KeySpec::Rsa4096 => (Nid::RSA, 512),
             KeySpec::EcP256 => (Nid::X9_62_PRIME256V1, 256),
             KeySpec::EcP256K => (Nid::SECP256K1, 256),
+            KeySpec::EcP384 => (Nid::SECP384R1, 384),
+            KeySpec::EcP521 => (Nid::SECP521R1, 521),
+            KeySpec::EcSm2 => (Nid::SM2, 256),
             KeySpec::SM4 => {
                 (Nid::from_raw(NID_sm4_cbc), Cipher::sm4_cbc().key_length())
             }
+            KeySpec::Ed25519 => (Nid::from_raw(NID_ED25519), 32),
+            KeySpec::Ed448 => (Nid::from_raw(NID_ED448), 57),
+            KeySpec::X25519 => (Nid::from_raw(NID_X25519), 32),
+            KeySpec::X448 => (Nid::from_raw(NID_X448), 56),
+            // hmac keys are as long as the digest output
+            KeySpec::HmacSha256 => (Nid::HMACWITHSHA256, 32),
+            KeySpec::HmacSha384 => (Nid::HMACWITHSHA384, 48),
+            KeySpec::HmacSha512 => (Nid::HMACWITHSHA512, 64),
+            KeySpec::HmacSm3 => (Nid::SM3, 32),
         }
     }
 }
@@ -290,6 +503,10 @@ pub enum WrappingKeyAlgorithm {
     RsaesOaepSha256,
     #[serde(rename = "SM2PKE")]
     SM2PKE,
+    // ephemeral aes-256 key wrapped by rsa-oaep-sha256, the material wrapped
+    // by rfc 5649 aes-kwp, CKM_RSA_AES_KEY_WRAP
+    #[serde(rename = "RSA_AES_KEY_WRAP_SHA_256")]
+    RsaAesKeyWrapSha256,
 }
 
 #[derive(
@@ -299,6 +516,8 @@ pub enum WrappingKeySpec {
     #[default]
     #[serde(rename = "RSA_2048")]
     Rsa2048,
+    #[serde(rename = "RSA_4096")]
+    Rsa4096,
     #[serde(rename = "EC_SM2")]
     EcSm2,
 }
@@ -307,11 +526,17 @@ impl From<WrappingKeySpec> for (Nid, usize) {
     fn from(value: WrappingKeySpec) -> Self {
         match value {
             WrappingKeySpec::Rsa2048 => (Nid::RSA, 256),
+            WrappingKeySpec::Rsa4096 => (Nid::RSA, 512),
             WrappingKeySpec::EcSm2 => (Nid::SM2, 256),
         }
     }
 }
 
-// 0.enable 1.disable 2.pendingdeletion 3.archived
-pub const KEY_STATE_MAP: [[bool; 3]; 3] =
-    [[true, true, true], [true, true, true], [false, true, true]];
+// 0.enable 1.disable 2.pendingdeletion 3.pendingimport
+// pending deletion is only entered and left by schedule / cancel deletion
+pub const KEY_STATE_MAP: [[bool; 4]; 4] = [
+    [true, true, false, false],
+    [true, true, false, false],
+    [false, false, true, false],
+    [false, false, false, true],
+];
@@ -0,0 +1,445 @@
+use anyhow::Context;
+use openssl::{
+    asn1, bn, hash,
+    nid::Nid,
+    pkey,
+    stack::Stack,
+    x509::{self, extension},
+};
+
+use super::{
+    algorithm,
+    types::{CertificateKeyUsage, ExtendedKeyUsage, KeyAlgorithm, KeySpec},
+};
+use crate::common::{
+    errors::{Result, ServiceError},
+    utils,
+};
+
+// rfc 5280 4.1.2.2, at most 20 octets
+pub const MAX_SERIAL_SIZE: usize = 20;
+
+// everything of a certificate but the subject and its public key, which are
+// taken from the csr
+pub struct CertificateProfile<'a> {
+    pub serial: &'a [u8],
+    // unix timestamps
+    pub not_before: i64,
+    pub not_after: i64,
+    pub ca: bool,
+    pub path_len: Option<u32>,
+    pub key_usage: &'a [CertificateKeyUsage],
+    pub extended_key_usage: &'a [ExtendedKeyUsage],
+    // DNS:, IP:, email: or URI: prefixed
+    pub subject_alt_names: &'a [String],
+}
+
+// the signature algorithm of csrs and certificates signed by a key spec,
+// rsa is signed with pkcs#1 v1.5 and eddsa without a digest, sm2 is left out
+// as its signature needs a distinguishing identifier openssl does not set
+pub fn x509_signature(
+    spec: KeySpec,
+) -> Result<(KeyAlgorithm, hash::MessageDigest)> {
+    let key_alg = match spec {
+        KeySpec::Rsa2048 | KeySpec::Rsa3072 | KeySpec::Rsa4096 => {
+            KeyAlgorithm::RsaPKCS1
+        }
+        KeySpec::EcP256
+        | KeySpec::EcP256K
+        | KeySpec::EcP384
+        | KeySpec::EcP521 => KeyAlgorithm::Ecdsa,
+        KeySpec::Ed25519 | KeySpec::Ed448 => {
+            return Ok((KeyAlgorithm::EdDSA, hash::MessageDigest::null()))
+        }
+        _ => {
+            return Err(ServiceError::Unsupported(format!(
+                "x509 signature is unsupported by key spec {:?}",
+                spec
+            )))
+        }
+    };
+    let digest = algorithm::select_algorithm_meta(spec)
+        .digest
+        .context("x509 signature requires message digest")?;
+    Ok((key_alg, digest.into()))
+}
+
+// random positive serial number, the high bit is cleared to keep the der
+// integer within size octets
+pub fn generate_serial(size: usize) -> Result<Vec<u8>> {
+    let mut serial = utils::generate_key(size.clamp(8, MAX_SERIAL_SIZE))?;
+    serial[0] &= 0x7f;
+    serial[0] |= 0x40;
+    Ok(serial)
+}
+
+pub fn build_name(entries: &[(Nid, &str)]) -> Result<x509::X509Name> {
+    let mut builder =
+        x509::X509NameBuilder::new().context("x509 name builder failed")?;
+    for (nid, value) in entries {
+        builder.append_entry_by_nid(*nid, value).map_err(|_| {
+            ServiceError::BadRequest(format!(
+                "x509 name entry is invalid, {}: {}",
+                nid.short_name().unwrap_or_default(),
+                value
+            ))
+        })?;
+    }
+    Ok(builder.build())
+}
+
+// rfc 4514 like, in the order of the name entries
+pub fn name_to_string(name: &x509::X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            format!(
+                "{}={}",
+                entry.object().nid().short_name().unwrap_or("UNDEF"),
+                entry.data().to_string().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// pkcs#10 certification request of the key, pem encoded
+pub fn create_csr(
+    spec: KeySpec,
+    private_key: &[u8],
+    subject: &x509::X509NameRef,
+    subject_alt_names: &[String],
+) -> Result<String> {
+    let (_key_alg, md) = x509_signature(spec)?;
+    let pkey = pkey::PKey::private_key_from_pkcs8(private_key)
+        .context("import private key pkcs8 to pkey failed")?;
+    let mut builder =
+        x509::X509ReqBuilder::new().context("x509 req builder failed")?;
+    builder.set_version(0).context("set csr version failed")?;
+    builder
+        .set_subject_name(subject)
+        .context("set csr subject failed")?;
+    builder
+        .set_pubkey(&pkey)
+        .context("set csr public key failed")?;
+    if !subject_alt_names.is_empty() {
+        let mut extensions =
+            Stack::new().context("csr extension stack failed")?;
+        let san = subject_alt_name(subject_alt_names)?
+            .build(&builder.x509v3_context(None))
+            .context("build subject alternative name failed")?;
+        extensions.push(san).context("push csr extension failed")?;
+        builder
+            .add_extensions(&extensions)
+            .context("add csr extensions failed")?;
+    }
+    builder.sign(&pkey, md).context("sign csr failed")?;
+    let pem = builder.build().to_pem().context("export csr pem failed")?;
+    Ok(String::from_utf8(pem).context("csr pem is not utf-8")?)
+}
+
+pub fn parse_csr(csr: &str) -> Result<x509::X509Req> {
+    let req = x509::X509Req::from_pem(csr.as_bytes()).map_err(|_| {
+        ServiceError::BadRequest("csr is not a pem pkcs#10 request".to_owned())
+    })?;
+    let public_key = req.public_key().map_err(|_| {
+        ServiceError::BadRequest("csr public key is invalid".to_owned())
+    })?;
+    if !req.verify(&public_key).unwrap_or(false) {
+        return Err(ServiceError::BadRequest(
+            "csr signature is invalid".to_owned(),
+        ));
+    }
+    Ok(req)
+}
+
+// signs the csr by the private key of spec, self-signed if issuer is absent,
+// the extensions of the csr are ignored in favour of the profile
+pub fn issue_certificate(
+    spec: KeySpec,
+    private_key: &[u8],
+    issuer: Option<&x509::X509Ref>,
+    req: &x509::X509ReqRef,
+    profile: &CertificateProfile,
+) -> Result<x509::X509> {
+    let (_key_alg, md) = x509_signature(spec)?;
+    let pkey = pkey::PKey::private_key_from_pkcs8(private_key)
+        .context("import private key pkcs8 to pkey failed")?;
+    let public_key = req.public_key().context("csr public key is invalid")?;
+
+    let mut builder =
+        x509::X509Builder::new().context("x509 builder failed")?;
+    builder.set_version(2).context("set x509 version failed")?;
+    let serial = bn::BigNum::from_slice(profile.serial)
+        .and_then(|serial| serial.to_asn1_integer())
+        .context("x509 serial number is invalid")?;
+    builder
+        .set_serial_number(&serial)
+        .context("set x509 serial number failed")?;
+    builder
+        .set_subject_name(req.subject_name())
+        .context("set x509 subject failed")?;
+    builder
+        .set_issuer_name(
+            issuer
+                .map(|issuer| issuer.subject_name())
+                .unwrap_or(req.subject_name()),
+        )
+        .context("set x509 issuer failed")?;
+    builder
+        .set_pubkey(&public_key)
+        .context("set x509 public key failed")?;
+    let not_before = asn1::Asn1Time::from_unix(profile.not_before)
+        .context("x509 not before is invalid")?;
+    builder
+        .set_not_before(&not_before)
+        .context("set x509 not before failed")?;
+    let not_after = asn1::Asn1Time::from_unix(profile.not_after)
+        .context("x509 not after is invalid")?;
+    builder
+        .set_not_after(&not_after)
+        .context("set x509 not after failed")?;
+
+    let mut basic_constraints = extension::BasicConstraints::new();
+    basic_constraints.critical();
+    if profile.ca {
+        basic_constraints.ca();
+        if let Some(path_len) = profile.path_len {
+            basic_constraints.pathlen(path_len);
+        }
+    }
+    let mut extensions = vec![basic_constraints.build()];
+    if !profile.key_usage.is_empty() {
+        extensions.push(key_usage(profile.key_usage).build());
+    }
+    if !profile.extended_key_usage.is_empty() {
+        extensions.push(extended_key_usage(profile.extended_key_usage).build());
+    }
+    for extension in extensions {
+        builder
+            .append_extension(extension.context("build x509 extension failed")?)
+            .context("append x509 extension failed")?;
+    }
+    // the key identifiers are computed from the subject and issuer in context
+    let ski = extension::SubjectKeyIdentifier::new()
+        .build(&builder.x509v3_context(issuer, None))
+        .context("build subject key identifier failed")?;
+    builder
+        .append_extension(ski)
+        .context("append x509 ski failed")?;
+    let aki = extension::AuthorityKeyIdentifier::new()
+        .keyid(true)
+        .build(&builder.x509v3_context(issuer, None))
+        .context("build authority key identifier failed")?;
+    builder
+        .append_extension(aki)
+        .context("append x509 aki failed")?;
+    if !profile.subject_alt_names.is_empty() {
+        let san = subject_alt_name(profile.subject_alt_names)?
+            .build(&builder.x509v3_context(issuer, None))
+            .context("build subject alternative name failed")?;
+        builder
+            .append_extension(san)
+            .context("append x509 san failed")?;
+    }
+
+    builder
+        .sign(&pkey, md)
+        .context("sign x509 certificate failed")?;
+    Ok(builder.build())
+}
+
+fn subject_alt_name(
+    names: &[String],
+) -> Result<extension::SubjectAlternativeName> {
+    let mut san = extension::SubjectAlternativeName::new();
+    for name in names {
+        match name.split_once(':') {
+            Some(("DNS", value)) => san.dns(value),
+            Some(("IP", value)) => san.ip(value),
+            Some(("email", value)) => san.email(value),
+            Some(("URI", value)) => san.uri(value),
+            _ => {
+                return Err(ServiceError::BadRequest(format!(
+                    "subject alternative name must be prefixed by DNS:, IP:, \
+                     email: or URI:, name: {}",
+                    name
+                )))
+            }
+        };
+    }
+    Ok(san)
+}
+
+fn key_usage(usages: &[CertificateKeyUsage]) -> extension::KeyUsage {
+    let mut key_usage = extension::KeyUsage::new();
+    key_usage.critical();
+    for usage in usages {
+        match usage {
+            CertificateKeyUsage::DigitalSignature => {
+                key_usage.digital_signature()
+            }
+            CertificateKeyUsage::NonRepudiation => key_usage.non_repudiation(),
+            CertificateKeyUsage::KeyEncipherment => {
+                key_usage.key_encipherment()
+            }
+            CertificateKeyUsage::DataEncipherment => {
+                key_usage.data_encipherment()
+            }
+            CertificateKeyUsage::KeyAgreement => key_usage.key_agreement(),
+            CertificateKeyUsage::KeyCertSign => key_usage.key_cert_sign(),
+            CertificateKeyUsage::CrlSign => key_usage.crl_sign(),
+        };
+    }
+    key_usage
+}
+
+fn extended_key_usage(
+    usages: &[ExtendedKeyUsage],
+) -> extension::ExtendedKeyUsage {
+    let mut extended_key_usage = extension::ExtendedKeyUsage::new();
+    for usage in usages {
+        match usage {
+            ExtendedKeyUsage::ServerAuth => extended_key_usage.server_auth(),
+            ExtendedKeyUsage::ClientAuth => extended_key_usage.client_auth(),
+            ExtendedKeyUsage::CodeSigning => extended_key_usage.code_signing(),
+            ExtendedKeyUsage::EmailProtection => {
+                extended_key_usage.email_protection()
+            }
+            ExtendedKeyUsage::TimeStamping => {
+                extended_key_usage.time_stamping()
+            }
+            ExtendedKeyUsage::OcspSigning => {
+                extended_key_usage.other("OCSPSigning")
+            }
+        };
+    }
+    extended_key_usage
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{nid::Nid, pkey, stack::Stack, x509};
+
+    use super::{
+        build_name, create_csr, generate_serial, issue_certificate,
+        name_to_string, parse_csr, CertificateProfile,
+    };
+    use crate::crypto::{
+        algorithm,
+        types::{CertificateKeyUsage, ExtendedKeyUsage, KeySpec},
+    };
+
+    #[test]
+    fn test_issue_certificate() {
+        for ca_spec in [KeySpec::EcP256, KeySpec::Ed25519, KeySpec::Rsa2048] {
+            // self-signed ca from the csr of its own key
+            let (ca_key, _) = algorithm::generate_key(ca_spec).unwrap();
+            let name = build_name(&[
+                (Nid::COUNTRYNAME, "CN"),
+                (Nid::ORGANIZATIONNAME, "heliannuuthus"),
+                (Nid::COMMONNAME, "kms root ca"),
+            ])
+            .unwrap();
+            let csr = create_csr(ca_spec, &ca_key, &name, &[]).unwrap();
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64;
+            let ca = issue_certificate(
+                ca_spec,
+                &ca_key,
+                None,
+                &parse_csr(&csr).unwrap(),
+                &CertificateProfile {
+                    serial: &generate_serial(16).unwrap(),
+                    not_before: now,
+                    not_after: now + 86400 * 3650,
+                    ca: true,
+                    path_len: Some(0),
+                    key_usage: &[
+                        CertificateKeyUsage::KeyCertSign,
+                        CertificateKeyUsage::CrlSign,
+                    ],
+                    extended_key_usage: &[],
+                    subject_alt_names: &[],
+                },
+            )
+            .unwrap();
+            assert!(ca.verify(&ca.public_key().unwrap()).unwrap());
+            assert_eq!(
+                name_to_string(ca.issuer_name()),
+                "C=CN,O=heliannuuthus,CN=kms root ca"
+            );
+
+            // leaf issued under the ca from an external csr
+            let (leaf_key, _) =
+                algorithm::generate_key(KeySpec::EcP384).unwrap();
+            let leaf_name =
+                build_name(&[(Nid::COMMONNAME, "kms.example.com")]).unwrap();
+            let csr = create_csr(KeySpec::EcP384, &leaf_key, &leaf_name, &[
+                "DNS:kms.example.com".to_owned(),
+            ])
+            .unwrap();
+            let serial = generate_serial(16).unwrap();
+            let leaf = issue_certificate(
+                ca_spec,
+                &ca_key,
+                Some(&ca),
+                &parse_csr(&csr).unwrap(),
+                &CertificateProfile {
+                    serial: &serial,
+                    not_before: now,
+                    not_after: now + 86400 * 365,
+                    ca: false,
+                    path_len: None,
+                    key_usage: &[CertificateKeyUsage::DigitalSignature],
+                    extended_key_usage: &[ExtendedKeyUsage::ServerAuth],
+                    subject_alt_names: &[
+                        "DNS:kms.example.com".to_owned(),
+                        "IP:10.0.0.1".to_owned(),
+                    ],
+                },
+            )
+            .unwrap();
+            assert!(leaf.verify(&ca.public_key().unwrap()).unwrap());
+            assert_eq!(leaf.serial_number().to_bn().unwrap().to_vec(), serial);
+            assert_eq!(
+                leaf.authority_key_id().unwrap().as_slice(),
+                ca.subject_key_id().unwrap().as_slice()
+            );
+            assert_eq!(leaf.subject_alt_names().unwrap().len(), 2);
+
+            // the chain is accepted by an x509 store of the ca
+            let mut store = x509::store::X509StoreBuilder::new().unwrap();
+            store.add_cert(ca.clone()).unwrap();
+            let store = store.build();
+            let mut ctx = x509::X509StoreContext::new().unwrap();
+            let valid = ctx
+                .init(&store, &leaf, &Stack::new().unwrap(), |ctx| {
+                    ctx.verify_cert()
+                })
+                .unwrap();
+            assert!(valid, "{:?}", ca_spec);
+        }
+    }
+
+    #[test]
+    fn test_parse_csr() {
+        let (key, _) = algorithm::generate_key(KeySpec::EcP256).unwrap();
+        let name = build_name(&[(Nid::COMMONNAME, "csr")]).unwrap();
+        let csr = create_csr(KeySpec::EcP256, &key, &name, &[]).unwrap();
+        let req = parse_csr(&csr).unwrap();
+        assert_eq!(
+            pkey::PKey::private_key_from_pkcs8(&key)
+                .unwrap()
+                .public_key_to_der()
+                .unwrap(),
+            req.public_key().unwrap().public_key_to_der().unwrap()
+        );
+        assert!(parse_csr("not a csr").is_err());
+        assert!(create_csr(KeySpec::EcP256, &key, &name, &[
+            "dns:lower.case".to_owned()
+        ])
+        .is_err());
+    }
+}
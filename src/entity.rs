@@ -1,5 +1,7 @@
+pub mod certificate;
 pub mod key;
 pub mod key_alias;
+pub mod key_history;
 pub mod key_meta;
 pub mod kms;
 pub mod prelude;
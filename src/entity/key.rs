@@ -45,15 +45,22 @@ impl Debug for Model {
     }
 }
 
+// wrapped marks the secret as encrypted under the root key, rows stored
+// before the root key existed lack it and are wrapped by the
+// `migrate-key-material` command
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SymmtricKeyPair {
     pub key_pair: String,
+    #[serde(default)]
+    pub wrapped: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct AsymmtricKeyPair {
     pub private_key: String,
     pub public_key: String,
+    #[serde(default)]
+    pub wrapped: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
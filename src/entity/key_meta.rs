@@ -36,6 +36,9 @@ pub struct Model {
     pub rotation_interval: i64,
     pub creator: String,
     pub material_expire_at: Option<DateTime>,
+    // sha-256 fingerprint of imported material, only identical material
+    // can be reimported
+    pub material_fingerprint: Option<String>,
     pub last_rotation_at: Option<DateTime>,
     pub deletion_at: Option<DateTime>,
     #[serde(skip)]
@@ -67,6 +70,7 @@ impl Default for Model {
             rotation_interval: Default::default(),
             creator: Default::default(),
             material_expire_at: Default::default(),
+            material_fingerprint: Default::default(),
             last_rotation_at: Default::default(),
             deletion_at: Default::default(),
             updated_at: Utc::now().naive_local(),
@@ -1,8 +1,14 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
+use anyhow::{anyhow, Context};
+use openssl::pkey;
 use serde_json::json;
 
 pub use super::{
+    certificate::{
+        Column as CertificateColumn, Entity as CertificateEntity,
+        Model as CertificateModel,
+    },
     key::{
         ActiveModel as KeyActiveModel, Column as KeyColumn,
         Entity as KeyEntity, Model as KeyModel,
@@ -11,16 +17,23 @@ pub use super::{
         Column as KeyAliasColumn, Entity as KeyAliasEntity,
         Model as KeyAliasModel,
     },
+    key_history::{
+        Column as KeyHistoryColumn, Entity as KeyHistoryEntity,
+        Model as KeyHistoryModel,
+    },
     key_meta::{
         Column as KeyMetaColumn, Entity as KeyMetaEntity, Model as KeyMetaModel,
     },
     kms::{Column as KmsColumn, Entity as KmsEntity, Model as KmsModel},
 };
 use crate::{
-    common::{errors::Result, utils},
+    common::{
+        errors::{Result, ServiceError},
+        utils,
+    },
     crypto::{
-        algorithm,
-        types::{KeySpec, KeyType},
+        algorithm, root_key,
+        types::{KeySpec, KeyState, KeyType},
     },
     entity::key::{AsymmtricKeyPair, SymmtricKeyPair},
 };
@@ -32,15 +45,166 @@ impl KeyModel {
         let pri_key = utils::encode64(&left);
         self.key_pair =
             Some(if KeyType::Symmetric.eq(&key_alg_meta.key_type) {
-                json!(SymmtricKeyPair { key_pair: pri_key })
+                json!(SymmtricKeyPair {
+                    key_pair: pri_key,
+                    wrapped: false
+                })
             } else {
                 json!(AsymmtricKeyPair {
                     private_key: pri_key,
-                    public_key: utils::encode64(&right)
+                    public_key: utils::encode64(&right),
+                    wrapped: false
                 })
             });
         Ok(self)
     }
+
+    // returns (private key, public key), symmetric keys return the same
+    // material for both
+    pub fn decode_key_pair(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        let key_pair = self.key_pair.clone().ok_or_else(|| {
+            ServiceError::StateChange(KeyState::PendingImport.into())
+        })?;
+        if KeyType::Symmetric.eq(&self.key_type) {
+            let pair = serde_json::from_value::<SymmtricKeyPair>(key_pair)
+                .context("deserialize symmetric key pair failed")?;
+            let key = utils::decode64(&pair.key_pair)?;
+            Ok((key.clone(), key))
+        } else {
+            let pair = serde_json::from_value::<AsymmtricKeyPair>(key_pair)
+                .context("deserialize asymmetric key pair failed")?;
+            Ok((
+                utils::decode64(&pair.private_key)?,
+                utils::decode64(&pair.public_key)?,
+            ))
+        }
+    }
+
+    // the public key is stored unwrapped, it is read without the root key
+    pub fn decode_public_key(&self) -> Result<Vec<u8>> {
+        let key_pair = self.key_pair.clone().ok_or_else(|| {
+            ServiceError::StateChange(KeyState::PendingImport.into())
+        })?;
+        if KeyType::Symmetric.eq(&self.key_type) {
+            return Err(ServiceError::BadRequest(format!(
+                "symmetric key has no public key, key_id: {}",
+                self.key_id
+            )));
+        }
+        let pair = serde_json::from_value::<AsymmtricKeyPair>(key_pair)
+            .context("deserialize asymmetric key pair failed")?;
+        utils::decode64(&pair.public_key)
+    }
+
+    // encrypt the secret material under the root key before it is stored
+    pub fn wrap_key_pair(self) -> Result<Self> {
+        let aad = self.root_key_aad();
+        self.map_secret(false, |secret| root_key::wrap(secret, &aad))
+    }
+
+    pub fn unwrap_key_pair(self) -> Result<Self> {
+        let aad = self.root_key_aad();
+        self.map_secret(true, |secret| root_key::unwrap(secret, &aad))
+    }
+
+    // wraps the material of a row stored before the root key existed,
+    // returns None if the row needs no migration
+    pub fn migrate_key_pair(self) -> Result<Option<Self>> {
+        let secret = match self.stored_secret()? {
+            Some((_, true)) | None => return Ok(None),
+            Some((secret, false)) => secret,
+        };
+        // wrapped before the marker was introduced, only the marker is absent
+        if root_key::unwrap(&secret, &self.root_key_aad()).is_ok() {
+            return self
+                .map_secret(false, |secret| Ok(secret.to_vec()))
+                .map(Some);
+        }
+        // a plaintext private key is pkcs#8, anything else is most likely
+        // material wrapped under another root key and must not be wrapped
+        // again
+        if KeyType::Asymmetric.eq(&self.key_type)
+            && pkey::PKey::private_key_from_pkcs8(&secret).is_err()
+        {
+            return Err(ServiceError::InternalServer(anyhow!(
+                "key material is neither wrapped under the root key nor a \
+                 pkcs#8 private key, key_id: {}, version: {}",
+                self.key_id,
+                self.version
+            )));
+        }
+        self.wrap_key_pair().map(Some)
+    }
+
+    fn root_key_aad(&self) -> Vec<u8> {
+        format!("{}:{}", self.key_id, self.version).into_bytes()
+    }
+
+    // the stored secret and whether it is wrapped under the root key
+    fn stored_secret(&self) -> Result<Option<(Vec<u8>, bool)>> {
+        let key_pair = match &self.key_pair {
+            Some(key_pair) => key_pair.clone(),
+            None => return Ok(None),
+        };
+        let (secret, wrapped) = if KeyType::Symmetric.eq(&self.key_type) {
+            let pair = serde_json::from_value::<SymmtricKeyPair>(key_pair)
+                .context("deserialize symmetric key pair failed")?;
+            (pair.key_pair, pair.wrapped)
+        } else {
+            let pair = serde_json::from_value::<AsymmtricKeyPair>(key_pair)
+                .context("deserialize asymmetric key pair failed")?;
+            (pair.private_key, pair.wrapped)
+        };
+        Ok(Some((utils::decode64(&secret)?, wrapped)))
+    }
+
+    // maps the secret of a key pair whose wrapped marker equals wrapped and
+    // flips the marker
+    fn map_secret<F>(mut self, wrapped: bool, f: F) -> Result<Self>
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>>,
+    {
+        let key_pair = match self.key_pair.take() {
+            Some(key_pair) => key_pair,
+            None => return Ok(self),
+        };
+        let assert_wrapped = |actual: bool| {
+            if actual == wrapped {
+                return Ok(());
+            }
+            Err(ServiceError::InternalServer(anyhow!(
+                "key material is {}wrapped under the root key, run \
+                 `migrate-key-material` for rows stored before the root key, \
+                 key_id: {}, version: {}",
+                if actual { "" } else { "not " },
+                self.key_id,
+                self.version
+            )))
+        };
+        self.key_pair = Some(if KeyType::Symmetric.eq(&self.key_type) {
+            let pair = serde_json::from_value::<SymmtricKeyPair>(key_pair)
+                .context("deserialize symmetric key pair failed")?;
+            assert_wrapped(pair.wrapped)?;
+            json!(SymmtricKeyPair {
+                key_pair: utils::encode64(&f(&utils::decode64(
+                    &pair.key_pair
+                )?)?),
+                wrapped: !wrapped,
+            })
+        } else {
+            let pair = serde_json::from_value::<AsymmtricKeyPair>(key_pair)
+                .context("deserialize asymmetric key pair failed")?;
+            assert_wrapped(pair.wrapped)?;
+            json!(AsymmtricKeyPair {
+                private_key: utils::encode64(&f(&utils::decode64(
+                    &pair.private_key
+                )?)?),
+                public_key: pair.public_key,
+                wrapped: !wrapped,
+            })
+        });
+        Ok(self)
+    }
 }
 
 impl KeyMetaModel {
@@ -62,3 +226,61 @@ impl KeyMetaModel {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::KeyModel;
+    use crate::{
+        common::utils,
+        crypto::{
+            root_key::{self, MemoryRootKey, ROOT_KEY_SIZE},
+            types::{KeySpec, KeyType},
+        },
+    };
+
+    #[test]
+    fn test_migrate_key_pair() {
+        root_key::install(Arc::new(
+            MemoryRootKey::new(utils::generate_key(ROOT_KEY_SIZE).unwrap())
+                .unwrap(),
+        ));
+        for (spec, key_type) in [
+            (KeySpec::Aes256, KeyType::Symmetric),
+            (KeySpec::EcP256, KeyType::Asymmetric),
+        ] {
+            let mut legacy = KeyModel {
+                key_id: utils::generate_b62(32).unwrap(),
+                version: utils::uuid(),
+                key_type,
+                ..Default::default()
+            };
+            legacy.generate_key(spec).unwrap();
+            let expected = legacy.decode_key_pair().unwrap();
+            let public_key = legacy.decode_public_key();
+            // rows stored before the root key are refused until migrated
+            assert!(legacy.clone().unwrap_key_pair().is_err());
+
+            let migrated = legacy.migrate_key_pair().unwrap().unwrap();
+            assert!(migrated.clone().migrate_key_pair().unwrap().is_none());
+            assert_eq!(
+                migrated
+                    .clone()
+                    .unwrap_key_pair()
+                    .unwrap()
+                    .decode_key_pair()
+                    .unwrap(),
+                expected
+            );
+            // the public key is read as stored, wrapped or not
+            assert_eq!(
+                migrated.decode_public_key().ok(),
+                public_key.as_ref().ok().cloned()
+            );
+            assert_eq!(public_key.is_ok(), KeyType::Asymmetric.eq(&key_type));
+            // wrapped material is never wrapped twice
+            assert!(migrated.wrap_key_pair().is_err());
+        }
+    }
+}
@@ -1,28 +1,46 @@
+use anyhow::Context;
 use axum::{
+    middleware,
     response::Html,
     routing::{delete, get, patch, post},
     Router,
 };
 use cache::prelude::{init as init_rd, RdConn};
-use common::{configs::env_var, log::init as init_log};
+use common::{
+    axum::{require_operator, require_unsealed},
+    configs::env_var,
+    log::init as init_log,
+};
 use controller::{
+    certificate_controller::{
+        create_csr, issue_certificate, list_certificates,
+    },
     crypto_controller::{
-        advance_encrypt, advance_sign, decrypt, encrypt, sign, verify,
+        advance_encrypt, advance_sign, decrypt, derive_shared_secret, encrypt,
+        generate_data_key, generate_data_key_without_plaintext, generate_mac,
+        re_encrypt, sign, sign_jws, verify, verify_mac,
     },
     key_alias_controller::{list_key_alias, remove_key_alias, set_key_alias},
     key_controller::{
-        create_key, create_key_version, import_key, import_key_params,
+        create_key, create_key_version, delete_key_material, get_public_key,
+        import_key, import_key_params,
     },
     key_meta_controller::{
-        change_key_state, get_key_meta, list_key_version, list_kms_keys,
-        set_key_meta,
+        cancel_key_deletion, change_key_state, get_key_meta, list_key_history,
+        list_key_version, list_kms_keys, schedule_key_deletion, set_key_meta,
     },
-    kms_controller::{create_kms, destroy_kms, get_kms, set_kms},
-    ApiDoc,
+    kms_controller::{create_kms, destroy_kms, get_jwks, get_kms, set_kms},
+    sys_controller, ApiDoc,
 };
 use dotenvy::dotenv;
+use pojo::form::sys::SysUnsealBody;
 use sea_orm::DbConn;
-use service::key_service::RotateExecutor;
+use service::{
+    key_service::{
+        self, DeletionExecutor, MaterialExpiryExecutor, RotateExecutor,
+    },
+    sys_service,
+};
 use utoipa::OpenApi;
 use utoipa_redoc::Redoc;
 
@@ -45,6 +63,7 @@ pub struct States {
 #[derive(Clone)]
 pub struct ExtraStates {
     re: RotateExecutor,
+    de: DeletionExecutor,
 }
 
 #[tokio::main]
@@ -53,46 +72,103 @@ async fn main() {
     let api_doc = openapi.to_pretty_json().unwrap();
     dotenv().expect(".env file not found");
     init_log();
+    // one-shot operator commands, run instead of the server, a failure is
+    // logged and exits non-zero
+    if let Some(command) = std::env::args().nth(1) {
+        let result = match command.as_str() {
+            "init-root-key" => init_root_key(),
+            "migrate-key-material" => migrate_key_material().await,
+            _ => {
+                tracing::error!("unknown command: {}", command);
+                std::process::exit(2);
+            }
+        };
+        if let Err(e) = result {
+            tracing::error!("{} failed: {}", command, e);
+            std::process::exit(2);
+        }
+        return;
+    }
+    crypto::root_key::init().unwrap();
     let db = common::datasource::init().await.unwrap();
     let rd = init_rd().await.unwrap();
     let executor = RotateExecutor::new(db.clone(), rd.clone()).await;
+    let deletion_executor = DeletionExecutor::new(db.clone(), rd.clone()).await;
+    let expiry_executor =
+        MaterialExpiryExecutor::new(db.clone(), rd.clone()).await;
     let state = States {
         db,
         rd,
         extra: ExtraStates {
             re: executor.clone(),
+            de: deletion_executor.clone(),
         },
     };
     tokio::spawn(async move {
         executor.poll_purge().await.unwrap();
     });
+    tokio::spawn(async move {
+        deletion_executor.poll_purge().await.unwrap();
+    });
+    tokio::spawn(async move {
+        expiry_executor.poll_purge().await.unwrap();
+    });
     let key_router = Router::new()
         .route("/", post(create_key))
         .route("/import", post(import_key))
-        .route("/import/params", get(import_key_params));
+        .route("/import/params", get(import_key_params))
+        .route_layer(middleware::from_fn(require_unsealed));
     let key_extra_router = Router::new()
         .route("/state", post(change_key_state))
+        .route("/deletion", post(schedule_key_deletion))
+        .route("/deletion/cancel", post(cancel_key_deletion))
         .route("/metas", post(set_key_meta))
         .route("/metas", get(get_key_meta))
         .route("/versions", post(create_key_version))
         .route("/versions", get(list_key_version))
+        .route("/histories", get(list_key_history))
+        .route("/material", delete(delete_key_material))
+        .route("/public-key", get(get_public_key))
         .route("/aliases", patch(set_key_alias))
         .route("/aliases", delete(remove_key_alias))
-        .route("/aliases", get(list_key_alias));
+        .route("/aliases", get(list_key_alias))
+        .route_layer(middleware::from_fn(require_unsealed));
     let crypto_router = Router::new()
         .route("/encrypt", post(advance_encrypt))
         .route("/decrypt", post(decrypt))
         .route("/encrypt/:version", post(encrypt))
+        .route("/reencrypt", post(re_encrypt))
+        .route("/datakey", post(generate_data_key))
+        .route(
+            "/datakey/without-plaintext",
+            post(generate_data_key_without_plaintext),
+        )
         .route("/sign", post(advance_sign))
         .route("/sign/:version", post(sign))
-        .route("/verify", post(verify));
+        .route("/verify", post(verify))
+        .route("/mac", post(generate_mac))
+        .route("/mac/verify", post(verify_mac))
+        .route("/derive-shared-secret", post(derive_shared_secret))
+        .route("/jws", post(sign_jws))
+        .route("/csr", post(create_csr))
+        .route("/certificates/issue", post(issue_certificate))
+        .route("/certificates", get(list_certificates))
+        .route_layer(middleware::from_fn(require_unsealed));
+    let sys_router = Router::new()
+        .route("/init", post(sys_controller::init))
+        .route("/seal", post(sys_controller::seal))
+        .route_layer(middleware::from_fn(require_operator))
+        .route("/unseal", post(sys_controller::unseal))
+        .route("/status", get(sys_controller::status));
     let kms_router = Router::new()
         .route("/", post(create_kms))
         .route("/:kms_id", patch(set_kms))
         .route("/:kms_id", get(get_kms))
         .route("/:kms_id", delete(destroy_kms))
-        .route("/:kms_id/keys", get(list_kms_keys));
+        .route("/:kms_id/keys", get(list_kms_keys))
+        .route("/:kms_id/.well-known/jwks.json", get(get_jwks));
     let app = Router::new()
+        .nest("/sys", sys_router)
         .nest("/kms", kms_router)
         .nest("/keys", key_router)
         .nest("/keys/:key_id/", key_extra_router)
@@ -116,3 +192,31 @@ async fn main() {
     .await
     .unwrap();
 }
+
+fn init_root_key() -> common::errors::Result<()> {
+    let path = crypto::root_key::root_key_file();
+    crypto::root_key::LocalFileProvider::generate(&path)?;
+    tracing::info!("root key file is created: {:?}", path);
+    Ok(())
+}
+
+// wraps the rows stored before the root key, shamir shares are read from
+// stdin, one per line, until the root key is unsealed
+async fn migrate_key_material() -> common::errors::Result<()> {
+    crypto::root_key::init()?;
+    let mut lines = std::io::stdin().lines();
+    while crypto::root_key::is_sealed() {
+        let share = lines
+            .next()
+            .context("root key is sealed, shares are exhausted")?
+            .context("read share from stdin failed")?;
+        sys_service::unseal(&SysUnsealBody {
+            share: share.trim().to_owned(),
+        })
+        .await?;
+    }
+    let db = common::datasource::init().await?;
+    let migrated = key_service::migrate_key_material(&db).await?;
+    tracing::info!("key material migration is done, rows: {}", migrated);
+    Ok(())
+}
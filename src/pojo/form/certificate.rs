@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct KeyCsrBody {
+    pub common_name: String,
+    // two letters iso 3166 code
+    pub country: Option<String>,
+    pub state: Option<String>,
+    pub locality: Option<String>,
+    pub organization: Option<String>,
+    pub organizational_unit: Option<String>,
+    pub email: Option<String>,
+
+    // DNS:, IP:, email: or URI: prefixed
+    #[serde(default)]
+    pub subject_alt_names: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct KeyCertificateIssueBody {
+    // pem encoded pkcs#10, self-signed if it is of the issuing key itself
+    pub csr: String,
+
+    // defaults to CERT_VALIDITY_DAYS, at most CERT_MAX_VALIDITY_DAYS
+    pub validity_days: Option<u32>,
+
+    // an external csr is only certified as an intermediate ca by the issuing
+    // keys listed in CERT_CA_ISSUERS, key usages and path length are
+    // configured server side
+    #[serde(default)]
+    pub ca: bool,
+
+    // hex encoded serial of the ca certificate of the issuing key to sign
+    // under, required once the key holds several valid ca certificates
+    pub issuer_serial: Option<String>,
+
+    // DNS:, IP:, email: or URI: prefixed, the extensions of the csr are not
+    // copied
+    #[serde(default)]
+    pub subject_alt_names: Vec<String>,
+}
@@ -1,32 +1,212 @@
 use std::fmt::Debug;
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::crypto::types::{KeyAlgorithm, Padding};
+use crate::crypto::types::{KeyAlgorithm, KeySpec, MessageDigest, MessageType};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct KeyEncryptBody {
-    pub key_id: String,
-
+    // base64 encoded
     pub plaintext: String,
 
+    pub algorithm: KeyAlgorithm,
+
+    // base64 encoded, only used by aead algorithms
+    pub aad: Option<String>,
+
+    // same plaintext and aad yield same ciphertext, only AES_SIV and
+    // AES_GCM_SIV are able to
+    #[serde(default)]
+    pub deterministic: bool,
+}
+
+impl Debug for KeyEncryptBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyEncryptBody")
+            .field("algorithm", &self.algorithm)
+            .field("deterministic", &self.deterministic)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct KeyDecryptBody {
+    // ciphertext blob returned by encrypt
     pub ciphertext: String,
 
+    // base64 encoded, must equal to the aad of encrypt
+    pub aad: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeySignBody {
+    // base64 encoded raw message or digest, depends on message_type
+    pub message: String,
+
+    pub algorithm: KeyAlgorithm,
+
+    // default digest of key spec if absent
+    pub digest: Option<MessageDigest>,
+
+    #[serde(default)]
+    pub message_type: MessageType,
+}
+
+impl Debug for KeySignBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeySignBody")
+            .field("algorithm", &self.algorithm)
+            .field("digest", &self.digest)
+            .field("message_type", &self.message_type)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyVerifyBody {
+    // primary version if absent
+    pub version: Option<String>,
+
+    // base64 encoded raw message or digest, depends on message_type
+    pub message: String,
+
+    // base64 encoded
+    pub signature: String,
+
     pub algorithm: KeyAlgorithm,
 
-    pub iv: String,
+    // default digest of key spec if absent
+    pub digest: Option<MessageDigest>,
+
+    #[serde(default)]
+    pub message_type: MessageType,
+}
+
+impl Debug for KeyVerifyBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyVerifyBody")
+            .field("version", &self.version)
+            .field("algorithm", &self.algorithm)
+            .field("digest", &self.digest)
+            .field("message_type", &self.message_type)
+            .finish()
+    }
+}
 
-    pub ada: String,
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyJwsBody {
+    // jwt claims, a json object
+    #[schema(value_type = Object)]
+    pub claims: serde_json::Value,
 
-    pub padding: Padding,
+    // RSA_PKCS1 (RS256), RSA_PSS (PS256), ECDSA (ES256/ES256K) or EDDSA
+    pub algorithm: KeyAlgorithm,
 }
 
-impl Debug for KeyEncryptBody {
+impl Debug for KeyJwsBody {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("KeyEncryptBody")
-            .field("key_id", &self.key_id)
+        f.debug_struct("KeyJwsBody")
             .field("algorithm", &self.algorithm)
-            .field("padding", &self.padding)
             .finish()
     }
 }
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyGenerateMacBody {
+    // base64 encoded
+    pub message: String,
+
+    pub algorithm: KeyAlgorithm,
+}
+
+impl Debug for KeyGenerateMacBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyGenerateMacBody")
+            .field("algorithm", &self.algorithm)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyVerifyMacBody {
+    // primary version if absent
+    pub version: Option<String>,
+
+    // base64 encoded
+    pub message: String,
+
+    // base64 encoded
+    pub mac: String,
+
+    pub algorithm: KeyAlgorithm,
+}
+
+impl Debug for KeyVerifyMacBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyVerifyMacBody")
+            .field("version", &self.version)
+            .field("algorithm", &self.algorithm)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyDeriveSharedSecretBody {
+    // primary version if absent
+    pub version: Option<String>,
+
+    // pem, or base64 encoded der spki or raw public key (sec1 point for ec
+    // curves, u coordinate for x25519/x448)
+    pub public_key: String,
+
+    // the shared secret is returned as a ciphertext blob under this key
+    // instead of plaintext if present
+    pub wrapping_key_id: Option<String>,
+
+    // required by wrapping_key_id
+    pub wrapping_algorithm: Option<KeyAlgorithm>,
+
+    // base64 encoded, only used by aead wrapping algorithms
+    pub aad: Option<String>,
+}
+
+impl Debug for KeyDeriveSharedSecretBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyDeriveSharedSecretBody")
+            .field("version", &self.version)
+            .field("wrapping_key_id", &self.wrapping_key_id)
+            .field("wrapping_algorithm", &self.wrapping_algorithm)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct KeyGenerateDataKeyBody {
+    // symmetric spec of the data key
+    pub spec: KeySpec,
+
+    // algorithm to wrap the data key under the master key
+    pub algorithm: KeyAlgorithm,
+
+    // base64 encoded, only used by aead algorithms
+    pub aad: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct KeyReEncryptBody {
+    // ciphertext blob returned by encrypt
+    pub ciphertext: String,
+
+    // base64 encoded, must equal to the aad of encrypt
+    pub source_aad: Option<String>,
+
+    // same key if absent
+    pub destination_key_id: Option<String>,
+
+    // same algorithm as the ciphertext if absent
+    pub destination_algorithm: Option<KeyAlgorithm>,
+
+    // base64 encoded, only used by aead algorithms
+    pub destination_aad: Option<String>,
+}
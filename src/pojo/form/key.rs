@@ -7,7 +7,8 @@ use utoipa::{IntoParams, ToSchema};
 
 use crate::{
     crypto::types::{
-        KeyOrigin, KeySpec, KeyUsage, WrappingKeyAlgorithm, WrappingKeySpec,
+        KeyOrigin, KeySpec, KeyUsage, PublicKeyFormat, WrappingKeyAlgorithm,
+        WrappingKeySpec,
     },
     entity::prelude::KeyMetaModel,
 };
@@ -51,6 +52,14 @@ pub struct KeyImportParamsQuery {
     pub wrapping_key_spec: WrappingKeySpec,
 }
 
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, IntoParams)]
+pub struct KeyPublicKeyQuery {
+    // primary version if absent
+    pub version: Option<String>,
+    #[serde(default)]
+    pub format: PublicKeyFormat,
+}
+
 #[serde_as]
 #[derive(Serialize, Deserialize, ToSchema, Clone)]
 pub struct KeyImportBody {
@@ -52,3 +52,9 @@ pub struct KeyChangeStateBody {
     pub from: KeyState,
     pub to: KeyState,
 }
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, Default)]
+pub struct KeyScheduleDeletionBody {
+    // 7 ~ 30 days, default 30 days
+    pub pending_window_in_days: Option<i64>,
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SysInitBody {
+    pub shares: u8,
+    pub threshold: u8,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SysUnsealBody {
+    pub share: String,
+}
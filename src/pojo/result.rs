@@ -1,4 +1,6 @@
+pub mod certificate;
 pub mod crypto;
 pub mod key;
 pub mod key_extra;
 pub mod kms;
+pub mod sys;
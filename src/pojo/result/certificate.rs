@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::entity::prelude::CertificateModel;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyCsrResult {
+    pub key_id: String,
+    pub version: String,
+    // pem encoded pkcs#10
+    pub csr: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyCertificateResult {
+    pub key_id: String,
+    pub version: String,
+    // hex encoded
+    pub serial: String,
+    pub subject: String,
+    pub issuer: String,
+    pub ca: bool,
+    pub not_before: NaiveDateTime,
+    pub not_after: NaiveDateTime,
+    // pem encoded
+    pub certificate: String,
+    // pem encoded issuer certificates from the issuer up to the root, empty
+    // for self-signed certificates
+    pub chain: String,
+}
+
+impl From<CertificateModel> for KeyCertificateResult {
+    fn from(value: CertificateModel) -> Self {
+        Self {
+            key_id: value.key_id,
+            version: value.version,
+            serial: value.serial,
+            subject: value.subject,
+            issuer: value.issuer,
+            ca: value.ca,
+            not_before: value.not_before,
+            not_after: value.not_after,
+            certificate: value.certificate,
+            chain: value.chain,
+        }
+    }
+}
@@ -1 +1,87 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyEncryptResult {
+    pub key_id: String,
+    pub version: String,
+    pub ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyDecryptResult {
+    pub key_id: String,
+    pub version: String,
+    pub plaintext: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeySignResult {
+    pub key_id: String,
+    pub version: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyVerifyResult {
+    pub key_id: String,
+    pub version: String,
+    pub valid: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyJwsResult {
+    pub key_id: String,
+    pub version: String,
+    pub kid: String,
+    // compact serialization
+    pub jws: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyGenerateMacResult {
+    pub key_id: String,
+    pub version: String,
+    pub mac: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyVerifyMacResult {
+    pub key_id: String,
+    pub version: String,
+    pub valid: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyDataKeyResult {
+    pub key_id: String,
+    pub version: String,
+    // ciphertext blob of the data key, decryptable by decrypt
+    pub ciphertext: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plaintext: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeySharedSecretResult {
+    pub key_id: String,
+    pub version: String,
+    // base64 encoded, absent if wrapped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shared_secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wrapping_key_id: Option<String>,
+    // ciphertext blob of the shared secret, decryptable by decrypt of the
+    // wrapping key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ciphertext: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct KeyReEncryptResult {
+    pub source_key_id: String,
+    pub source_version: String,
+    pub key_id: String,
+    pub version: String,
+    pub ciphertext: String,
+}
@@ -5,8 +5,8 @@ use utoipa::ToSchema;
 
 use crate::{
     crypto::types::{
-        KeyOrigin, KeySpec, KeyState, KeyType, KeyUsage, WrappingKeyAlgorithm,
-        WrappingKeySpec,
+        KeyAlgorithm, KeyOrigin, KeySpec, KeyState, KeyType, KeyUsage,
+        PublicKeyFormat, WrappingKeyAlgorithm, WrappingKeySpec,
     },
     entity::prelude::*,
 };
@@ -96,3 +96,17 @@ impl From<KeyMetaModel> for KeyVersionResult {
         }
     }
 }
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct KeyPublicKeyResult {
+    pub key_id: String,
+    pub version: String,
+    pub key_spec: KeySpec,
+    pub key_usage: KeyUsage,
+    pub format: PublicKeyFormat,
+    // base64 encoded der, pem or openssh text, or a jwk object
+    #[schema(value_type = Object)]
+    pub public_key: serde_json::Value,
+    pub signing_algorithms: Vec<KeyAlgorithm>,
+    pub encryption_algorithms: Vec<KeyAlgorithm>,
+}
@@ -1 +1,23 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
+use crate::{crypto::types::KeyState, entity::prelude::*};
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct KeyDeletionResult {
+    pub key_id: String,
+    pub state: KeyState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deletion_at: Option<NaiveDateTime>,
+}
+
+impl From<KeyMetaModel> for KeyDeletionResult {
+    fn from(value: KeyMetaModel) -> Self {
+        KeyDeletionResult {
+            key_id: value.key_id,
+            state: value.state,
+            deletion_at: value.deletion_at,
+        }
+    }
+}
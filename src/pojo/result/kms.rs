@@ -7,3 +7,10 @@ pub struct KmsResult {
     pub name: String,
     pub description: Option<String>,
 }
+
+// rfc 7517 jwk set
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct JwksResult {
+    #[schema(value_type = Vec<Object>)]
+    pub keys: Vec<serde_json::Value>,
+}
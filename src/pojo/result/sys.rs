@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SysInitResult {
+    pub shares: Vec<String>,
+    pub threshold: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SysStatusResult {
+    pub initialized: bool,
+    pub sealed: bool,
+    pub shares: u8,
+    pub threshold: u8,
+    pub progress: usize,
+}
@@ -1,4 +1,6 @@
+pub mod certificate_repository;
 pub mod key_alias_repository;
+pub mod key_history_repository;
 pub mod key_meta_repository;
 pub mod key_repository;
 pub mod kms_repository;
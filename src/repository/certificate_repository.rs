@@ -0,0 +1,65 @@
+use anyhow::Context;
+use chrono::NaiveDateTime;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder,
+};
+
+use crate::{
+    common::{datasource, datasource::Paginator, errors::Result},
+    entity::prelude::*,
+    pagin,
+};
+
+pub async fn insert_certificate<C: ConnectionTrait>(
+    db: &C,
+    model: CertificateModel,
+) -> Result<()> {
+    CertificateEntity::insert(model.into_active_model())
+        .exec(db)
+        .await
+        .context("insert certificate failed")?;
+    Ok(())
+}
+
+// the ca certificates of a public key that are still valid at now, narrowed
+// to one serial if it is named
+pub async fn select_ca_certificates<C: ConnectionTrait>(
+    db: &C,
+    public_key_fingerprint: &str,
+    serial: Option<&str>,
+    now: NaiveDateTime,
+) -> Result<Vec<CertificateModel>> {
+    let mut condition = CertificateColumn::PublicKeyFingerprint
+        .eq(public_key_fingerprint)
+        .and(CertificateColumn::Ca.eq(true))
+        .and(CertificateColumn::NotBefore.lte(now))
+        .and(CertificateColumn::NotAfter.gt(now));
+    if let Some(serial) = serial {
+        condition = condition.and(CertificateColumn::Serial.eq(serial));
+    }
+    Ok(CertificateEntity::find()
+        .filter(condition)
+        .order_by_desc(CertificateColumn::NotAfter)
+        .all(db)
+        .await
+        .context(format!(
+            "select ca certificates failed, fingerprint: {}",
+            public_key_fingerprint
+        ))?)
+}
+
+pub async fn pagin_certificate<C: ConnectionTrait>(
+    db: &C,
+    key_id: &str,
+    paginator: Paginator,
+) -> Result<Vec<CertificateModel>> {
+    pagin!(
+        db,
+        paginator,
+        CertificateEntity::find()
+            .filter(CertificateColumn::KeyId.eq(key_id))
+            .cursor_by(CertificateColumn::Id),
+        format!("pagin certificates failed, key_id: {}", key_id)
+    )
+}
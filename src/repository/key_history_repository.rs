@@ -0,0 +1,36 @@
+use anyhow::Context;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+};
+
+use crate::{
+    common::{datasource, datasource::Paginator, errors::Result},
+    entity::prelude::*,
+    pagin,
+};
+
+pub async fn insert_history<C: ConnectionTrait>(
+    db: &C,
+    model: KeyHistoryModel,
+) -> Result<()> {
+    KeyHistoryEntity::insert(model.into_active_model())
+        .exec(db)
+        .await
+        .context("insert key history failed")?;
+    Ok(())
+}
+
+pub async fn pagin_key_history<C: ConnectionTrait>(
+    db: &C,
+    key_id: &str,
+    paginator: Paginator,
+) -> Result<Vec<KeyHistoryModel>> {
+    pagin!(
+        db,
+        paginator,
+        KeyHistoryEntity::find()
+            .filter(KeyHistoryColumn::KeyId.eq(key_id))
+            .cursor_by(KeyHistoryColumn::Id),
+        format!("pagin key histories failed, key_id: {}", key_id)
+    )
+}
@@ -1,10 +1,15 @@
 use anyhow::Context;
+use chrono::NaiveDateTime;
 use sea_orm::{
     sea_query::OnConflict, ColumnTrait, ConnectionTrait, EntityTrait,
     IntoActiveModel, QueryFilter,
 };
 
-use crate::{common::errors::Result, entity::prelude::*};
+use crate::{
+    common::errors::Result,
+    crypto::types::{KeyOrigin, KeyState},
+    entity::prelude::*,
+};
 
 // batch insert metas
 pub async fn insert_or_update_key_metas<C: ConnectionTrait>(
@@ -23,6 +28,7 @@ pub async fn insert_or_update_key_metas<C: ConnectionTrait>(
                 KeyMetaColumn::PrimaryVersion,
                 KeyMetaColumn::LastRotationAt,
                 KeyMetaColumn::MaterialExpireAt,
+                KeyMetaColumn::MaterialFingerprint,
                 KeyMetaColumn::DeletionAt,
             ])
             .to_owned(),
@@ -43,6 +49,37 @@ pub async fn select_key_meta<C: ConnectionTrait>(
         .context(format!("select key meta failed, key_id: {}", key_id))?)
 }
 
+// metas of imported material expired before the given time
+pub async fn select_expired_material_metas<C: ConnectionTrait>(
+    db: &C,
+    before: NaiveDateTime,
+) -> Result<Vec<KeyMetaModel>> {
+    Ok(KeyMetaEntity::find()
+        .filter(KeyMetaColumn::Origin.eq(KeyOrigin::External))
+        .filter(KeyMetaColumn::MaterialExpireAt.lte(before))
+        .all(db)
+        .await
+        .context(format!(
+            "select expired material metas failed, before: {}",
+            before
+        ))?)
+}
+
+pub async fn select_due_deletion_metas<C: ConnectionTrait>(
+    db: &C,
+    before: NaiveDateTime,
+) -> Result<Vec<KeyMetaModel>> {
+    Ok(KeyMetaEntity::find()
+        .filter(KeyMetaColumn::State.eq(KeyState::PendingDeletion))
+        .filter(KeyMetaColumn::DeletionAt.lte(before))
+        .all(db)
+        .await
+        .context(format!(
+            "select due deletion metas failed, before: {}",
+            before
+        ))?)
+}
+
 pub async fn select_key_meta_by_kms<C: ConnectionTrait>(
     db: &C,
     kms_id: &str,
@@ -56,3 +93,15 @@ pub async fn select_key_meta_by_kms<C: ConnectionTrait>(
             kms_id
         ))?)
 }
+
+pub async fn delete_key_metas<C: ConnectionTrait>(
+    db: &C,
+    key_id: &str,
+) -> Result<()> {
+    KeyMetaEntity::delete_many()
+        .filter(KeyMetaColumn::KeyId.eq(key_id))
+        .exec(db)
+        .await
+        .context(format!("delete key metas failed, key_id: {}", key_id))?;
+    Ok(())
+}
@@ -35,3 +35,30 @@ pub async fn select_key<C: ConnectionTrait>(
         .all(db)
         .await?)
 }
+
+// a batch of keys ordered by the primary key, starting after the given one
+pub async fn select_keys_after<C: ConnectionTrait>(
+    db: &C,
+    after: i64,
+    limit: u64,
+) -> Result<Vec<KeyModel>> {
+    Ok(KeyEntity::find()
+        .filter(KeyColumn::Id.gt(after))
+        .order_by_asc(KeyColumn::Id)
+        .limit(limit)
+        .all(db)
+        .await
+        .context(format!("select keys failed, after: {}", after))?)
+}
+
+pub async fn delete_keys<C: ConnectionTrait>(
+    db: &C,
+    key_id: &str,
+) -> Result<()> {
+    KeyEntity::delete_many()
+        .filter(KeyColumn::KeyId.eq(key_id))
+        .exec(db)
+        .await
+        .context(format!("delete keys failed, key_id: {}", key_id))?;
+    Ok(())
+}
@@ -1,4 +1,8 @@
+pub mod certificate_service;
+pub mod crypto_service;
 pub mod key_alias_service;
+pub mod key_history_service;
 pub mod key_meta_service;
 pub mod key_service;
 pub mod kms_service;
+pub mod sys_service;
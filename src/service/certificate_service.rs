@@ -0,0 +1,339 @@
+use anyhow::Context;
+use chrono::{Duration, NaiveDateTime, Utc};
+use openssl::{nid::Nid, sha, x509 as openssl_x509};
+use sea_orm::DbConn;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use super::{crypto_service, key_meta_service};
+use crate::{
+    cache::prelude::RdConn,
+    common::{
+        configs::env_var_default,
+        datasource::{self, PaginatedResult, Paginator},
+        errors::{Result, ServiceError},
+    },
+    crypto::{
+        types::CertificateKeyUsage,
+        x509::{self, CertificateProfile},
+    },
+    entity::prelude::*,
+    paginated_result,
+    pojo::{
+        form::certificate::{KeyCertificateIssueBody, KeyCsrBody},
+        result::certificate::{KeyCertificateResult, KeyCsrResult},
+    },
+    repository::certificate_repository,
+};
+
+const CERT_VALIDITY_DAYS: &str = "CERT_VALIDITY_DAYS";
+// upper bound of the validity, self-signed roots included
+const CERT_MAX_VALIDITY_DAYS: &str = "CERT_MAX_VALIDITY_DAYS";
+const CERT_SERIAL_BYTES: &str = "CERT_SERIAL_BYTES";
+// comma separated key ids allowed to certify external csrs as intermediate
+// cas, intermediates get path length 0
+const CERT_CA_ISSUERS: &str = "CERT_CA_ISSUERS";
+const CERT_ROOT_PATH_LEN: &str = "CERT_ROOT_PATH_LEN";
+// comma separated usages of end entity certificates
+const CERT_LEAF_KEY_USAGE: &str = "CERT_LEAF_KEY_USAGE";
+const CERT_LEAF_EXTENDED_KEY_USAGE: &str = "CERT_LEAF_EXTENDED_KEY_USAGE";
+
+pub async fn create_csr(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    body: &KeyCsrBody,
+) -> Result<KeyCsrResult> {
+    let meta = key_meta_service::get_main_key_meta(rd, db, key_id).await?;
+    let (key_alg, _) = x509::x509_signature(meta.spec)?;
+    let (meta, key) =
+        crypto_service::get_usable_key(rd, db, key_id, None, key_alg).await?;
+    let (private_key, _public_key) = key.decode_key_pair()?;
+
+    let mut entries = vec![];
+    for (nid, value) in [
+        (Nid::COUNTRYNAME, &body.country),
+        (Nid::STATEORPROVINCENAME, &body.state),
+        (Nid::LOCALITYNAME, &body.locality),
+        (Nid::ORGANIZATIONNAME, &body.organization),
+        (Nid::ORGANIZATIONALUNITNAME, &body.organizational_unit),
+    ] {
+        if let Some(value) = value {
+            entries.push((nid, value.as_str()));
+        }
+    }
+    entries.push((Nid::COMMONNAME, body.common_name.as_str()));
+    if let Some(email) = &body.email {
+        entries.push((Nid::PKCS9_EMAILADDRESS, email.as_str()));
+    }
+    let subject = x509::build_name(&entries)?;
+
+    let csr = x509::create_csr(
+        meta.spec,
+        &private_key,
+        &subject,
+        &body.subject_alt_names,
+    )?;
+    Ok(KeyCsrResult {
+        key_id: key.key_id,
+        version: key.version,
+        csr,
+    })
+}
+
+// a csr of the issuing key itself is self-signed, any other csr is signed
+// under a valid ca certificate issued to the issuing key, the certificate
+// profile is configured server side
+pub async fn issue_certificate(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    body: &KeyCertificateIssueBody,
+) -> Result<KeyCertificateResult> {
+    let validity_days = body
+        .validity_days
+        .unwrap_or_else(|| env_var_default(CERT_VALIDITY_DAYS, 365));
+    let max_validity_days = env_var_default(CERT_MAX_VALIDITY_DAYS, 3650);
+    if validity_days == 0 || validity_days > max_validity_days {
+        return Err(ServiceError::BadRequest(format!(
+            "validity_days must be in 1 ..= {}, actual: {}",
+            max_validity_days, validity_days
+        )));
+    }
+    let meta = key_meta_service::get_main_key_meta(rd, db, key_id).await?;
+    let (key_alg, _) = x509::x509_signature(meta.spec)?;
+    let (meta, key) =
+        crypto_service::get_usable_key(rd, db, key_id, None, key_alg).await?;
+    let (private_key, public_key) = key.decode_key_pair()?;
+
+    let req = x509::parse_csr(&body.csr)?;
+    let subject_public_key = req
+        .public_key()
+        .and_then(|pkey| pkey.public_key_to_der())
+        .context("export csr public key der failed")?;
+    let self_signed = subject_public_key.eq(&public_key);
+    if body.ca && !self_signed && !is_ca_issuer(key_id) {
+        return Err(ServiceError::BadRequest(format!(
+            "the key is not allowed to issue ca certificates, key_id: {}",
+            key_id
+        )));
+    }
+
+    let not_before = truncate(Utc::now().naive_local());
+    let not_after = not_before
+        .checked_add_signed(Duration::days(validity_days as i64))
+        .ok_or_else(|| {
+            ServiceError::BadRequest(format!(
+                "validity_days is out of range: {}",
+                validity_days
+            ))
+        })?;
+    let (issuer, chain) = if self_signed {
+        (None, String::new())
+    } else {
+        let ca = select_issuer(
+            db,
+            key_id,
+            &public_key,
+            body.issuer_serial.as_deref(),
+            not_before,
+        )
+        .await?;
+        if not_after > ca.not_after {
+            return Err(ServiceError::BadRequest(format!(
+                "certificate outlives its ca certificate, ca not_after: {}",
+                ca.not_after
+            )));
+        }
+        let issuer = openssl_x509::X509::from_pem(ca.certificate.as_bytes())
+            .context("import ca certificate pem failed")?;
+        if body.ca && issuer.pathlen() == Some(0) {
+            return Err(ServiceError::BadRequest(format!(
+                "ca certificate forbids intermediate cas, serial: {}",
+                ca.serial
+            )));
+        }
+        (Some(issuer), ca.certificate + &ca.chain)
+    };
+
+    // roots leave room for intermediates, intermediates only sign leaves
+    let (path_len, key_usage, extended_key_usage) = if body.ca {
+        (
+            Some(if self_signed {
+                env_var_default(CERT_ROOT_PATH_LEN, 1)
+            } else {
+                0
+            }),
+            vec![
+                CertificateKeyUsage::KeyCertSign,
+                CertificateKeyUsage::CrlSign,
+            ],
+            vec![],
+        )
+    } else {
+        (
+            None,
+            configured_usages(CERT_LEAF_KEY_USAGE, "DIGITAL_SIGNATURE")?,
+            configured_usages(CERT_LEAF_EXTENDED_KEY_USAGE, "")?,
+        )
+    };
+    let serial = x509::generate_serial(env_var_default(CERT_SERIAL_BYTES, 16))?;
+    let certificate = x509::issue_certificate(
+        meta.spec,
+        &private_key,
+        issuer.as_deref(),
+        &req,
+        &CertificateProfile {
+            serial: &serial,
+            not_before: not_before.and_utc().timestamp(),
+            not_after: not_after.and_utc().timestamp(),
+            ca: body.ca,
+            path_len,
+            key_usage: &key_usage,
+            extended_key_usage: &extended_key_usage,
+            subject_alt_names: &body.subject_alt_names,
+        },
+    )?;
+    let pem = certificate
+        .to_pem()
+        .context("export certificate pem failed")?;
+
+    let model = CertificateModel {
+        key_id: key.key_id,
+        version: key.version,
+        serial: hex::encode(&serial),
+        subject: x509::name_to_string(certificate.subject_name()),
+        issuer: x509::name_to_string(certificate.issuer_name()),
+        public_key_fingerprint: fingerprint(&subject_public_key),
+        ca: body.ca,
+        not_before,
+        not_after,
+        certificate: String::from_utf8(pem)
+            .context("certificate pem is not utf-8")?,
+        chain,
+        ..Default::default()
+    };
+    certificate_repository::insert_certificate(db, model.clone()).await?;
+    Ok(model.into())
+}
+
+// the ca certificate of the issuing key named by its serial, without a
+// serial only an unambiguous one is taken
+async fn select_issuer(
+    db: &DbConn,
+    key_id: &str,
+    public_key: &[u8],
+    serial: Option<&str>,
+    now: NaiveDateTime,
+) -> Result<CertificateModel> {
+    let serial = serial.map(str::to_lowercase);
+    let mut cas = certificate_repository::select_ca_certificates(
+        db,
+        &fingerprint(public_key),
+        serial.as_deref(),
+        now,
+    )
+    .await?;
+    match (cas.len(), serial) {
+        (1, _) => Ok(cas.remove(0)),
+        (0, Some(serial)) => Err(ServiceError::BadRequest(format!(
+            "no valid ca certificate of the key has the serial, key_id: {}, \
+             serial: {}",
+            key_id, serial
+        ))),
+        (0, None) => Err(ServiceError::BadRequest(format!(
+            "no valid ca certificate is issued to the key, self-sign one \
+             first, key_id: {}",
+            key_id
+        ))),
+        _ => Err(ServiceError::BadRequest(format!(
+            "several valid ca certificates are issued to the key, name one by \
+             issuer_serial, key_id: {}",
+            key_id
+        ))),
+    }
+}
+
+fn is_ca_issuer(key_id: &str) -> bool {
+    env_var_default::<String>(CERT_CA_ISSUERS, String::new())
+        .split(',')
+        .any(|issuer| issuer.trim().eq(key_id))
+}
+
+fn configured_usages<T: DeserializeOwned>(
+    name: &str,
+    default_value: &str,
+) -> Result<Vec<T>> {
+    env_var_default::<String>(name, default_value.to_owned())
+        .split(',')
+        .map(str::trim)
+        .filter(|usage| !usage.is_empty())
+        .map(|usage| {
+            serde_json::from_value::<T>(json!(usage))
+                .context(format!("{} env variable is invalid: {}", name, usage))
+        })
+        .collect::<std::result::Result<Vec<T>, _>>()
+        .map_err(ServiceError::InternalServer)
+}
+
+pub async fn list_certificates(
+    db: &DbConn,
+    key_id: &str,
+    paginator: Paginator,
+) -> Result<PaginatedResult<Vec<CertificateModel>>> {
+    let mut result = certificate_repository::pagin_certificate(
+        db,
+        key_id,
+        paginator.clone(),
+    )
+    .await?;
+    paginated_result!(result, paginator.limit.unwrap_or(10))
+}
+
+fn fingerprint(public_key: &[u8]) -> String {
+    hex::encode(sha::sha256(public_key))
+}
+
+// x509 validity is in seconds
+fn truncate(at: NaiveDateTime) -> NaiveDateTime {
+    at - Duration::nanoseconds(at.and_utc().timestamp_subsec_nanos() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{configured_usages, is_ca_issuer, CERT_CA_ISSUERS};
+    use crate::crypto::types::{CertificateKeyUsage, ExtendedKeyUsage};
+
+    #[test]
+    fn test_configured_profile() {
+        assert_eq!(
+            configured_usages::<CertificateKeyUsage>(
+                "CERT_TEST_KEY_USAGE",
+                "DIGITAL_SIGNATURE, KEY_AGREEMENT"
+            )
+            .unwrap(),
+            vec![
+                CertificateKeyUsage::DigitalSignature,
+                CertificateKeyUsage::KeyAgreement
+            ]
+        );
+        assert!(configured_usages::<ExtendedKeyUsage>(
+            "CERT_TEST_EXTENDED_KEY_USAGE",
+            ""
+        )
+        .unwrap()
+        .is_empty());
+        assert!(configured_usages::<ExtendedKeyUsage>(
+            "CERT_TEST_EXTENDED_KEY_USAGE",
+            "SERVER_AUTH,ANY"
+        )
+        .is_err());
+
+        // no key issues ca certificates unless it is listed
+        assert!(!is_ca_issuer("issuer"));
+        std::env::set_var(CERT_CA_ISSUERS, "root, issuer");
+        assert!(is_ca_issuer("issuer"));
+        assert!(!is_ca_issuer("iss"));
+        std::env::remove_var(CERT_CA_ISSUERS);
+    }
+}
@@ -0,0 +1,691 @@
+use chrono::Utc;
+use itertools::Itertools;
+use sea_orm::DbConn;
+
+use super::{key_meta_service, key_service, kms_service};
+use crate::{
+    cache::prelude::RdConn,
+    common::{
+        errors::{Result, ServiceError},
+        utils,
+    },
+    crypto::{
+        algorithm::{self, CryptoAdaptor, EncryptKits},
+        blob::CiphertextBlob,
+        ecdh, jws, public_key,
+        types::{KeyAlgorithm, KeySpec, KeyState, KeyUsage},
+    },
+    entity::prelude::*,
+    pojo::{
+        form::crypto::{
+            KeyDecryptBody, KeyDeriveSharedSecretBody, KeyEncryptBody,
+            KeyGenerateDataKeyBody, KeyGenerateMacBody, KeyJwsBody,
+            KeyReEncryptBody, KeySignBody, KeyVerifyBody, KeyVerifyMacBody,
+        },
+        result::{
+            crypto::{
+                KeyDataKeyResult, KeyDecryptResult, KeyEncryptResult,
+                KeyGenerateMacResult, KeyJwsResult, KeyReEncryptResult,
+                KeySharedSecretResult, KeySignResult, KeyVerifyMacResult,
+                KeyVerifyResult,
+            },
+            kms::JwksResult,
+        },
+    },
+};
+
+pub async fn encrypt(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    version: Option<&str>,
+    body: &KeyEncryptBody,
+) -> Result<KeyEncryptResult> {
+    let (_meta, key) =
+        get_usable_key(rd, db, key_id, version, body.algorithm).await?;
+    let (_private_key, public_key) = key.decode_key_pair()?;
+    let plaintext = utils::decode64(&body.plaintext)?;
+    let aad = decode_aad(&body.aad)?;
+
+    let blob = encrypt_blob(
+        &key,
+        &public_key,
+        &plaintext,
+        aad,
+        body.algorithm,
+        body.deterministic,
+    )?;
+    Ok(KeyEncryptResult {
+        key_id: key.key_id,
+        version: key.version,
+        ciphertext: blob.encode()?,
+    })
+}
+
+pub async fn decrypt(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    body: &KeyDecryptBody,
+) -> Result<KeyDecryptResult> {
+    let blob = CiphertextBlob::decode(&body.ciphertext)?;
+    if !blob.key_id.eq(key_id) {
+        return Err(ServiceError::BadRequest(format!(
+            "ciphertext is not encrypted by key, key_id: {}",
+            key_id
+        )));
+    }
+    let (_meta, key) =
+        get_usable_key(rd, db, key_id, Some(&blob.version), blob.algorithm)
+            .await?;
+    let (private_key, _public_key) = key.decode_key_pair()?;
+    let aad = decode_aad(&body.aad)?;
+
+    let plaintext = decrypt_blob(&private_key, &blob, aad)?;
+    Ok(KeyDecryptResult {
+        key_id: key.key_id,
+        version: key.version,
+        plaintext: utils::encode64(&plaintext),
+    })
+}
+
+// the plaintext is only held in memory between decrypt and encrypt
+pub async fn re_encrypt(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    body: &KeyReEncryptBody,
+) -> Result<KeyReEncryptResult> {
+    let source = CiphertextBlob::decode(&body.ciphertext)?;
+    if !source.key_id.eq(key_id) {
+        return Err(ServiceError::BadRequest(format!(
+            "ciphertext is not encrypted by key, key_id: {}",
+            key_id
+        )));
+    }
+    let (_meta, source_key) =
+        get_usable_key(rd, db, key_id, Some(&source.version), source.algorithm)
+            .await?;
+
+    let destination_key_id =
+        body.destination_key_id.as_deref().unwrap_or(key_id);
+    let destination_algorithm =
+        body.destination_algorithm.unwrap_or(source.algorithm);
+    let (_meta, destination_key) =
+        get_usable_key(rd, db, destination_key_id, None, destination_algorithm)
+            .await?;
+
+    let (private_key, _public_key) = source_key.decode_key_pair()?;
+    let plaintext =
+        decrypt_blob(&private_key, &source, decode_aad(&body.source_aad)?)?;
+    let (_private_key, public_key) = destination_key.decode_key_pair()?;
+    let blob = encrypt_blob(
+        &destination_key,
+        &public_key,
+        &plaintext,
+        decode_aad(&body.destination_aad)?,
+        destination_algorithm,
+        false,
+    )?;
+    Ok(KeyReEncryptResult {
+        source_key_id: source_key.key_id,
+        source_version: source_key.version,
+        key_id: destination_key.key_id,
+        version: destination_key.version,
+        ciphertext: blob.encode()?,
+    })
+}
+
+pub async fn generate_data_key(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    body: &KeyGenerateDataKeyBody,
+    with_plaintext: bool,
+) -> Result<KeyDataKeyResult> {
+    if !matches!(body.spec, KeySpec::Aes128 | KeySpec::Aes256 | KeySpec::SM4) {
+        return Err(ServiceError::Unsupported(format!(
+            "data key spec must be AES_128, AES_256 or SM4, spec: {:?}",
+            body.spec
+        )));
+    }
+    let data_key_meta = algorithm::select_algorithm_meta(body.spec);
+    let (_meta, key) =
+        get_usable_key(rd, db, key_id, None, body.algorithm).await?;
+    let (_private_key, public_key) = key.decode_key_pair()?;
+    let aad = decode_aad(&body.aad)?;
+
+    let data_key = utils::generate_key(data_key_meta.key_size)?;
+    let blob =
+        encrypt_blob(&key, &public_key, &data_key, aad, body.algorithm, false)?;
+    Ok(KeyDataKeyResult {
+        key_id: key.key_id,
+        version: key.version,
+        ciphertext: blob.encode()?,
+        plaintext: with_plaintext.then(|| utils::encode64(&data_key)),
+    })
+}
+
+pub async fn sign(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    version: Option<&str>,
+    body: &KeySignBody,
+) -> Result<KeySignResult> {
+    let (meta, key) =
+        get_usable_key(rd, db, key_id, version, body.algorithm).await?;
+    let (private_key, _public_key) = key.decode_key_pair()?;
+    let message = utils::decode64(&body.message)?;
+    let adaptor = algorithm::select_sign_adaptor(
+        meta.spec,
+        body.algorithm,
+        body.digest,
+        body.message_type,
+    )?;
+    assert_digest_size(&adaptor, &message)?;
+
+    let signature = algorithm::select_factory(body.algorithm)?.sign(
+        &private_key,
+        &message,
+        &adaptor,
+    )?;
+    Ok(KeySignResult {
+        key_id: key.key_id,
+        version: key.version,
+        signature: utils::encode64(&signature),
+    })
+}
+
+pub async fn verify(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    body: &KeyVerifyBody,
+) -> Result<KeyVerifyResult> {
+    let (meta, key) =
+        get_usable_key(rd, db, key_id, body.version.as_deref(), body.algorithm)
+            .await?;
+    let (_private_key, public_key) = key.decode_key_pair()?;
+    let message = utils::decode64(&body.message)?;
+    let signature = utils::decode64(&body.signature)?;
+    let adaptor = algorithm::select_sign_adaptor(
+        meta.spec,
+        body.algorithm,
+        body.digest,
+        body.message_type,
+    )?;
+    assert_digest_size(&adaptor, &message)?;
+
+    let valid = algorithm::select_factory(body.algorithm)?.verify(
+        &public_key,
+        &message,
+        &signature,
+        &adaptor,
+    )?;
+    Ok(KeyVerifyResult {
+        key_id: key.key_id,
+        version: key.version,
+        valid,
+    })
+}
+
+// always signed by the primary version, the kid tells verifiers which jwk
+// of the jwks to pick
+pub async fn sign_jws(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    body: &KeyJwsBody,
+) -> Result<KeyJwsResult> {
+    let (meta, key) =
+        get_usable_key(rd, db, key_id, None, body.algorithm).await?;
+    let (private_key, _public_key) = key.decode_key_pair()?;
+    let kid = public_key::key_version_kid(&key.key_id, &key.version);
+
+    let jws = jws::sign_compact(
+        meta.spec,
+        body.algorithm,
+        &private_key,
+        &kid,
+        &body.claims,
+    )?;
+    Ok(KeyJwsResult {
+        key_id: key.key_id,
+        version: key.version,
+        kid,
+        jws,
+    })
+}
+
+// public keys of every version of the enabled jws capable signing keys,
+// versions whose imported material expired are left out
+pub async fn get_jwks(
+    rd: &RdConn,
+    db: &DbConn,
+    kms_id: &str,
+) -> Result<JwksResult> {
+    kms_service::get_kms(rd, db, kms_id).await?;
+    let now = Utc::now().naive_local();
+    let metas = key_meta_service::get_key_meta_by_kms(db, kms_id).await?;
+    let signing_key_ids = metas
+        .iter()
+        .filter(|meta| {
+            meta.version.eq(&meta.primary_version)
+                && KeyState::Enabled.eq(&meta.state)
+                && KeyUsage::SignAndVerify.eq(&meta.usage)
+                && algorithm::select_algorithm_meta(meta.spec)
+                    .key_algorithms
+                    .into_iter()
+                    .any(|alg| jws::jws_algorithm(meta.spec, alg).is_ok())
+        })
+        .map(|meta| meta.key_id.as_str())
+        .collect_vec();
+
+    let mut keys = vec![];
+    for key_id in signing_key_ids {
+        // public keys are stored unwrapped, the jwks is served while sealed
+        let versions = key_service::get_public_keys(db, key_id).await?;
+        for meta in metas.iter().filter(|meta| {
+            meta.key_id.eq(key_id)
+                && meta.material_expire_at.is_none_or(|at| at > now)
+        }) {
+            let key =
+                match versions.iter().find(|key| key.version.eq(&meta.version))
+                {
+                    Some(key) if key.key_pair.is_some() => key,
+                    _ => continue,
+                };
+            let public_key = key.decode_public_key()?;
+            keys.push(public_key::to_jwk(
+                meta.spec,
+                meta.usage,
+                &public_key,
+                &public_key::key_version_kid(&key.key_id, &key.version),
+            )?);
+        }
+    }
+    Ok(JwksResult { keys })
+}
+
+pub async fn generate_mac(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    body: &KeyGenerateMacBody,
+) -> Result<KeyGenerateMacResult> {
+    let (meta, key) =
+        get_usable_key(rd, db, key_id, None, body.algorithm).await?;
+    let (private_key, _public_key) = key.decode_key_pair()?;
+    let message = utils::decode64(&body.message)?;
+    let adaptor = algorithm::select_mac_adaptor(meta.spec)?;
+
+    let mac = algorithm::select_factory(body.algorithm)?.sign(
+        &private_key,
+        &message,
+        &adaptor,
+    )?;
+    Ok(KeyGenerateMacResult {
+        key_id: key.key_id,
+        version: key.version,
+        mac: utils::encode64(&mac),
+    })
+}
+
+pub async fn verify_mac(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    body: &KeyVerifyMacBody,
+) -> Result<KeyVerifyMacResult> {
+    let (meta, key) =
+        get_usable_key(rd, db, key_id, body.version.as_deref(), body.algorithm)
+            .await?;
+    let (_private_key, public_key) = key.decode_key_pair()?;
+    let message = utils::decode64(&body.message)?;
+    let mac = utils::decode64(&body.mac)?;
+    let adaptor = algorithm::select_mac_adaptor(meta.spec)?;
+
+    let valid = algorithm::select_factory(body.algorithm)?.verify(
+        &public_key,
+        &message,
+        &mac,
+        &adaptor,
+    )?;
+    Ok(KeyVerifyMacResult {
+        key_id: key.key_id,
+        version: key.version,
+        valid,
+    })
+}
+
+// the private key never leaves the kms, the shared secret does unless it is
+// wrapped under another key of the kms
+pub async fn derive_shared_secret(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    body: &KeyDeriveSharedSecretBody,
+) -> Result<KeySharedSecretResult> {
+    let (meta, key) = get_usable_key(
+        rd,
+        db,
+        key_id,
+        body.version.as_deref(),
+        KeyAlgorithm::Ecdh,
+    )
+    .await?;
+    let peer_public_key = if body.public_key.starts_with("-----BEGIN") {
+        body.public_key.as_bytes().to_vec()
+    } else {
+        utils::decode64(&body.public_key)?
+    };
+    let (private_key, _public_key) = key.decode_key_pair()?;
+    let shared_secret =
+        ecdh::derive_shared_secret(meta.spec, &private_key, &peer_public_key)?;
+
+    let ciphertext = match (&body.wrapping_key_id, body.wrapping_algorithm) {
+        (None, _) => None,
+        (Some(_), None) => {
+            return Err(ServiceError::BadRequest(
+                "wrapping_algorithm is required by wrapping_key_id".to_owned(),
+            ))
+        }
+        (Some(wrapping_key_id), Some(alg)) => {
+            let (_meta, wrapping_key) =
+                get_usable_key(rd, db, wrapping_key_id, None, alg).await?;
+            let (_private_key, public_key) = wrapping_key.decode_key_pair()?;
+            let blob = encrypt_blob(
+                &wrapping_key,
+                &public_key,
+                &shared_secret,
+                decode_aad(&body.aad)?,
+                alg,
+                false,
+            )?;
+            Some(blob.encode()?)
+        }
+    };
+    Ok(KeySharedSecretResult {
+        key_id: key.key_id,
+        version: key.version,
+        shared_secret: ciphertext
+            .is_none()
+            .then(|| utils::encode64(&shared_secret)),
+        wrapping_key_id: body.wrapping_key_id.clone(),
+        ciphertext,
+    })
+}
+
+pub fn encrypt_blob(
+    key: &KeyModel,
+    public_key: &[u8],
+    plaintext: &[u8],
+    aad: Vec<u8>,
+    alg: KeyAlgorithm,
+    deterministic: bool,
+) -> Result<CiphertextBlob> {
+    let mut adaptor =
+        algorithm::select_encrypt_adaptor(alg, public_key, aad, deterministic)?;
+    let ciphertext = algorithm::select_factory(alg)?.encrypt(
+        public_key,
+        plaintext,
+        &mut adaptor,
+    )?;
+    let kits = adaptor.kits.unwrap_or_default();
+    Ok(CiphertextBlob {
+        key_id: key.key_id.to_owned(),
+        version: key.version.to_owned(),
+        algorithm: alg,
+        iv: kits.iv,
+        tag: kits.tag,
+        ciphertext,
+    })
+}
+
+pub fn decrypt_blob(
+    private_key: &[u8],
+    blob: &CiphertextBlob,
+    aad: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let mut adaptor: CryptoAdaptor = blob.algorithm.into();
+    adaptor.kits = Some(EncryptKits {
+        iv: blob.iv.clone(),
+        aad,
+        tag: blob.tag.clone(),
+    });
+    algorithm::select_factory(blob.algorithm)?.decrypt(
+        private_key,
+        &blob.ciphertext,
+        &adaptor,
+    )
+}
+
+// resolve the key of version (primary version if absent) and make sure it
+// can be used by the algorithm
+pub async fn get_usable_key(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    version: Option<&str>,
+    alg: KeyAlgorithm,
+) -> Result<(KeyMetaModel, KeyModel)> {
+    let meta = key_meta_service::get_main_key_meta(rd, db, key_id).await?;
+    assert_usable(&meta, alg)?;
+    let version = version.unwrap_or(&meta.primary_version);
+    // imported material expires per version
+    if !meta.primary_version.eq(version) {
+        assert_material_unexpired(
+            &key_meta_service::get_version_key_meta(rd, db, key_id, version)
+                .await?,
+        )?;
+    }
+    let key = key_service::get_version_key(db, key_id, version).await?;
+    Ok((meta, key))
+}
+
+pub fn assert_usable(meta: &KeyMetaModel, alg: KeyAlgorithm) -> Result<()> {
+    if !KeyState::Enabled.eq(&meta.state) {
+        if KeyState::PendingDeletion.eq(&meta.state) {
+            tracing::warn!(
+                "key pending deletion is used, key_id: {}, algorithm: {:?}, \
+                 deletion_at: {:?}",
+                meta.key_id,
+                alg,
+                meta.deletion_at
+            );
+        }
+        return Err(ServiceError::StateChange(meta.state.into()));
+    }
+    let usage: KeyUsage = alg.usage();
+    if !meta.usage.eq(&usage) {
+        return Err(ServiceError::BadRequest(format!(
+            "key usage is {:?}, but {:?} requires {:?}",
+            meta.usage, alg, usage
+        )));
+    }
+    if !algorithm::select_algorithm_meta(meta.spec)
+        .key_algorithms
+        .contains(&alg)
+    {
+        return Err(ServiceError::Unsupported(format!(
+            "{:?} is unsupported by key spec {:?}",
+            alg, meta.spec
+        )));
+    }
+    assert_material_unexpired(meta)
+}
+
+// expired material is refused at once, the expiry sweep only erases it
+// later, the key then is pending import as well
+pub fn assert_material_unexpired(meta: &KeyMetaModel) -> Result<()> {
+    match meta.material_expire_at {
+        Some(expire_at) if expire_at <= Utc::now().naive_local() => {
+            tracing::warn!(
+                "expired key material is used, key_id: {}, version: {}, \
+                 expire_at: {}",
+                meta.key_id,
+                meta.version,
+                expire_at
+            );
+            Err(ServiceError::StateChange(KeyState::PendingImport.into()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn assert_digest_size(adaptor: &CryptoAdaptor, message: &[u8]) -> Result<()> {
+    match adaptor.md {
+        Some(md) if adaptor.prehashed && md.size() != message.len() => {
+            Err(ServiceError::BadRequest(format!(
+                "digest length is invalid, expect: {}, actual: {}",
+                md.size(),
+                message.len()
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn decode_aad(aad: &Option<String>) -> Result<Vec<u8>> {
+    aad.as_deref()
+        .map(utils::decode64)
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use openssl::hash;
+
+    use super::{assert_usable, decrypt_blob, encrypt_blob};
+    use crate::{
+        common::errors::ServiceError,
+        crypto::{
+            algorithm,
+            blob::CiphertextBlob,
+            types::{
+                KeyAlgorithm, KeyOrigin, KeySpec, KeyState, KeyUsage,
+                MessageType,
+            },
+        },
+        entity::prelude::{KeyMetaModel, KeyModel},
+    };
+
+    #[test]
+    fn test_assert_usable_material_expiry() {
+        let mut meta = KeyMetaModel {
+            spec: KeySpec::Aes256,
+            origin: KeyOrigin::External,
+            state: KeyState::Enabled,
+            usage: KeyUsage::EncryptAndDecrypt,
+            material_expire_at: Some(
+                Utc::now().naive_local() + Duration::minutes(1),
+            ),
+            ..Default::default()
+        };
+        assert!(assert_usable(&meta, KeyAlgorithm::AesGCM).is_ok());
+        // refused before the expiry sweep erases the material
+        meta.material_expire_at =
+            Some(Utc::now().naive_local() - Duration::seconds(1));
+        assert!(assert_usable(&meta, KeyAlgorithm::AesGCM).is_err());
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_blob() {
+        for (spec, alg) in [
+            (KeySpec::Aes128, KeyAlgorithm::AesGCM),
+            (KeySpec::Aes256, KeyAlgorithm::AesCBC),
+            (KeySpec::SM4, KeyAlgorithm::Sm4CTR),
+            (KeySpec::SM4, KeyAlgorithm::Sm4GCM),
+            (KeySpec::SM4, KeyAlgorithm::Sm4CCM),
+            (KeySpec::Rsa2048, KeyAlgorithm::RsaOAEP),
+            (KeySpec::EcP384, KeyAlgorithm::EciesHkdfAesGCM),
+            (KeySpec::Aes256, KeyAlgorithm::AesSIV),
+            (KeySpec::Aes128, KeyAlgorithm::AesGcmSIV),
+        ] {
+            let mut key = KeyModel {
+                key_id: "key_id".to_owned(),
+                key_type: algorithm::select_algorithm_meta(spec).key_type,
+                version: "version".to_owned(),
+                ..Default::default()
+            };
+            key.generate_key(spec).unwrap();
+            let (private_key, public_key) = key.decode_key_pair().unwrap();
+            let blob = match encrypt_blob(
+                &key,
+                &public_key,
+                b"plaintext",
+                b"aad".to_vec(),
+                alg,
+                false,
+            ) {
+                // the provider of the linked openssl may not offer it
+                Err(ServiceError::Unsupported(_))
+                    if algorithm::select_fetched_cipher(
+                        private_key.len(),
+                        alg,
+                    )
+                    .is_ok() =>
+                {
+                    continue
+                }
+                blob => blob.unwrap(),
+            };
+            let blob = CiphertextBlob::decode(&blob.encode().unwrap()).unwrap();
+            assert_eq!(blob.algorithm, alg);
+            assert_eq!(
+                decrypt_blob(&private_key, &blob, b"aad".to_vec()).unwrap(),
+                b"plaintext"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        for (spec, alg) in [
+            (KeySpec::Rsa2048, KeyAlgorithm::RsaPSS),
+            (KeySpec::Rsa3072, KeyAlgorithm::RsaPKCS1),
+            (KeySpec::EcP256, KeyAlgorithm::Ecdsa),
+            (KeySpec::EcP256K, KeyAlgorithm::Ecdsa),
+            (KeySpec::EcP384, KeyAlgorithm::Ecdsa),
+            (KeySpec::EcP521, KeyAlgorithm::Ecdsa),
+        ] {
+            let (private_key, public_key) =
+                algorithm::generate_key(spec).unwrap();
+            let factory = algorithm::select_factory(alg).unwrap();
+            let raw = algorithm::select_sign_adaptor(
+                spec,
+                alg,
+                None,
+                MessageType::Raw,
+            )
+            .unwrap();
+            let digested = algorithm::select_sign_adaptor(
+                spec,
+                alg,
+                None,
+                MessageType::Digest,
+            )
+            .unwrap();
+            let digest =
+                hash::hash(raw.md.unwrap(), b"plaintext").unwrap().to_vec();
+
+            // signature of raw message and its digest are interchangeable
+            let signature =
+                factory.sign(&private_key, b"plaintext", &raw).unwrap();
+            assert!(factory
+                .verify(&public_key, &digest, &signature, &digested)
+                .unwrap());
+            let signature =
+                factory.sign(&private_key, &digest, &digested).unwrap();
+            assert!(factory
+                .verify(&public_key, b"plaintext", &signature, &raw)
+                .unwrap());
+            assert!(!factory
+                .verify(&public_key, b"tampered", &signature, &raw)
+                .unwrap());
+        }
+    }
+}
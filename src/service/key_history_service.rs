@@ -0,0 +1,49 @@
+use sea_orm::DbConn;
+
+use crate::{
+    common::{
+        datasource::{self, PaginatedResult, Paginator},
+        errors::Result,
+    },
+    entity::{key_history::KeyHistoryAction, prelude::*},
+    paginated_result,
+    repository::key_history_repository,
+};
+
+// the history is best effort, a failed record never fails the action
+pub async fn record<T>(
+    db: &DbConn,
+    key_id: &str,
+    action: KeyHistoryAction,
+    result: &Result<T>,
+) {
+    let model = KeyHistoryModel {
+        key_id: key_id.to_owned(),
+        action,
+        success: result.is_ok(),
+        detail: result.as_ref().err().map(|e| e.to_string()),
+        ..Default::default()
+    };
+    if let Err(e) = key_history_repository::insert_history(db, model).await {
+        tracing::error!(
+            "record key history failed, key_id: {}, action: {:?}, error: {:?}",
+            key_id,
+            action,
+            e
+        );
+    }
+}
+
+pub async fn list_key_histories(
+    db: &DbConn,
+    key_id: &str,
+    paginator: Paginator,
+) -> Result<PaginatedResult<Vec<KeyHistoryModel>>> {
+    let mut result = key_history_repository::pagin_key_history(
+        db,
+        key_id,
+        paginator.clone(),
+    )
+    .await?;
+    paginated_result!(result, paginator.limit.unwrap_or(10))
+}
@@ -8,6 +8,7 @@ use sea_orm::*;
 use serde_json::json;
 
 use super::{
+    crypto_service, key_history_service,
     key_meta_service::{self, get_main_key_meta},
     kms_service,
 };
@@ -23,21 +24,27 @@ use crate::{
     },
     crypto::{
         algorithm::{self},
-        types::{self, KeyOrigin, KeyState, KeyType},
+        public_key,
+        types::{self, KeyOrigin, KeyState, KeyType, KeyUsage},
     },
     encode_key,
     entity::{
         key::{AsymmtricKeyPair, SymmtricKeyPair},
+        key_history::KeyHistoryAction,
         prelude::*,
     },
     pojo::{
-        form::key::{KeyImportBody, KeyImportParamsQuery},
-        result::key::{
-            KeyCreateResult, KeyMaterialImportParams,
-            KeyMaterialImportParamsResult, KeyVersionResult,
+        form::key::{KeyImportBody, KeyImportParamsQuery, KeyPublicKeyQuery},
+        result::{
+            key::{
+                KeyCreateResult, KeyMaterialImportParams,
+                KeyMaterialImportParamsResult, KeyPublicKeyResult,
+                KeyVersionResult,
+            },
+            key_extra::KeyDeletionResult,
         },
     },
-    repository::key_repository,
+    repository::{key_alias_repository, key_meta_repository, key_repository},
 };
 
 pub const KEY_CACHE_KEY: &str = "key_cache";
@@ -138,6 +145,148 @@ impl RotateExecutor {
     }
 }
 
+#[derive(Clone)]
+pub struct DeletionExecutor {
+    db: DbConn,
+    rd: RdConn,
+}
+
+impl DeletionExecutor {
+    pub async fn new(db: DbConn, rd: RdConn) -> Self {
+        DeletionExecutor { db, rd }
+    }
+
+    fn key(&self) -> String {
+        "kms:keys:deletion".to_owned()
+    }
+
+    pub async fn submit(
+        &self,
+        key_id: &str,
+        deletion_at: NaiveDateTime,
+    ) -> Result<()> {
+        let mut conn = rdconn(&self.rd).await?;
+        conn.zadd(self.key(), key_id, deletion_at.and_utc().timestamp())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove(&self, key_id: &str) -> Result<()> {
+        let mut conn = rdconn(&self.rd).await?;
+        conn.zrem(self.key(), key_id).await?;
+        Ok(())
+    }
+
+    pub async fn poll_purge(&self) -> Result<()> {
+        let default_interval =
+            configs::env_var_default::<i64>("DEFAULT_DELETION_INTERVAL", 60);
+        let mut delay = tokio::time::interval(
+            Duration::seconds(default_interval).to_std().unwrap(),
+        );
+        // errors are logged and retried on the next tick, a failed tick
+        // never stops the purger
+        loop {
+            delay.tick().await;
+            let now = Utc::now().naive_local();
+            let mut key_ids = self.due_key_ids(now).await.unwrap_or_else(|e| {
+                tracing::error!(
+                    "load deletion schedule failed, error: {:?}",
+                    e
+                );
+                vec![]
+            });
+            // the sweep over deletion_at catches keys whose schedule is lost
+            match key_meta_repository::select_due_deletion_metas(&self.db, now)
+                .await
+            {
+                Ok(metas) => {
+                    key_ids.extend(metas.into_iter().map(|meta| meta.key_id))
+                }
+                Err(e) => tracing::error!(
+                    "sweep due deletion keys failed, error: {:?}",
+                    e
+                ),
+            }
+            for key_id in key_ids.into_iter().unique() {
+                let result = match purge_key(&self.rd, &self.db, &key_id).await
+                {
+                    Ok(_) => self.remove(&key_id).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    tracing::error!(
+                        "purge key failed, key_id: {}, error: {:?}",
+                        key_id,
+                        e
+                    )
+                }
+            }
+        }
+    }
+
+    async fn due_key_ids(&self, now: NaiveDateTime) -> Result<Vec<String>> {
+        let mut conn = rdconn(&self.rd).await?;
+        Ok(conn
+            .zrangebyscore(self.key(), 0, now.and_utc().timestamp())
+            .await?)
+    }
+}
+
+#[derive(Clone)]
+pub struct MaterialExpiryExecutor {
+    db: DbConn,
+    rd: RdConn,
+}
+
+impl MaterialExpiryExecutor {
+    pub async fn new(db: DbConn, rd: RdConn) -> Self {
+        MaterialExpiryExecutor { db, rd }
+    }
+
+    pub async fn poll_purge(&self) -> Result<()> {
+        let default_interval = configs::env_var_default::<i64>(
+            "DEFAULT_MATERIAL_EXPIRY_INTERVAL",
+            60,
+        );
+        let mut delay = tokio::time::interval(
+            Duration::seconds(default_interval).to_std().unwrap(),
+        );
+        loop {
+            delay.tick().await;
+            // a failed sweep is retried on the next tick
+            let metas =
+                match key_meta_repository::select_expired_material_metas(
+                    &self.db,
+                    Utc::now().naive_local(),
+                )
+                .await
+                {
+                    Ok(metas) => metas,
+                    Err(e) => {
+                        tracing::error!(
+                            "sweep expired key material failed, error: {:?}",
+                            e
+                        );
+                        continue;
+                    }
+                };
+            for meta in metas {
+                let key_id = meta.key_id.to_owned();
+                if let Err(e) =
+                    erase_key_material(&self.rd, &self.db, meta).await
+                {
+                    tracing::error!(
+                        "erase expired key material failed, key_id: {}, \
+                         error: {:?}",
+                        key_id,
+                        e
+                    )
+                }
+            }
+        }
+    }
+}
+
 pub async fn create_key(
     rd: &RdConn,
     db: &DbConn,
@@ -198,15 +347,23 @@ pub async fn generate_key_import_params(
         )));
     }
 
+    // reject the unmatched wrapping spec and algorithm before key generation
+    algorithm::select_wrapping_factory(
+        form.wrapping_key_spec,
+        form.wrapping_algorithm,
+    )?;
+
     let (left, right) =
         algorithm::generate_wrapping_key(form.wrapping_key_spec)?;
 
     let expires_in = Duration::days(1);
 
+    // every parameter set is keyed by its own token, outstanding sets of the
+    // same key never overwrite each other
     let import_token = utils::generate_b64(128)?;
     let mut conn = rdconn(rd).await?;
     conn.set_ex(
-        format!("kms:keys:import_material:{}", key_id),
+        import_params_key(key_id, &import_token),
         serde_json::to_string(&KeyMaterialImportParams {
             token: import_token.to_owned(),
             private_key: utils::encode64(&left),
@@ -225,35 +382,44 @@ pub async fn generate_key_import_params(
     })
 }
 
+fn import_params_key(key_id: &str, token: &str) -> String {
+    format!("kms:keys:import_material:{}:{}", key_id, token)
+}
+
 pub async fn import_key_material(
     rd: &RdConn,
     db: &DbConn,
     data: &KeyImportBody,
 ) -> Result<()> {
-    let key_id = &data.key_id;
-    let material_data = match redis_get::<KeyMaterialImportParams>(
-        rd,
-        &format!("kms:keys:import_material:{}", key_id),
+    let result = do_import_key_material(rd, db, data).await;
+    key_history_service::record(
+        db,
+        &data.key_id,
+        KeyHistoryAction::ImportKeyMaterial,
+        &result,
     )
-    .await?
-    {
-        Some(import_material_data) => {
-            if !import_material_data.token.eq(&data.import_token) {
-                return Err(ServiceError::BadRequest(
-                    "token is unmatched".to_owned(),
-                ));
-            }
-            import_material_data
-        }
-        None => {
-            return Err(ServiceError::NotFount(format!(
-                "material is not created or token is expired, key_id: {}",
-                key_id,
-            )))
-        }
-    };
+    .await;
+    result
+}
 
-    let f = algorithm::select_wrapping_factory(material_data.wrapping_spec);
+async fn do_import_key_material(
+    rd: &RdConn,
+    db: &DbConn,
+    data: &KeyImportBody,
+) -> Result<()> {
+    let key_id = &data.key_id;
+    let params_key = import_params_key(key_id, &data.import_token);
+    let material_data = redis_get::<KeyMaterialImportParams>(rd, &params_key)
+        .await?
+        .ok_or(ServiceError::NotFount(format!(
+            "material is not created or token is expired, key_id: {}",
+            key_id,
+        )))?;
+
+    let f = algorithm::select_wrapping_factory(
+        material_data.wrapping_spec,
+        material_data.wrapping_algorithm,
+    )?;
 
     let private_key = f.decrypt(
         &utils::decode64(&material_data.private_key)?,
@@ -261,9 +427,9 @@ pub async fn import_key_material(
         &material_data.wrapping_algorithm.into(),
     )?;
 
-    let key_model: KeyModel = get_main_key(rd, db, key_id).await?;
+    let mut key_model: KeyModel = get_main_key(rd, db, key_id).await?;
 
-    let key_meta_model = key_meta_service::get_version_key_meta(
+    let mut key_meta_model = key_meta_service::get_version_key_meta(
         rd,
         db,
         key_id,
@@ -271,33 +437,117 @@ pub async fn import_key_material(
     )
     .await?;
 
-    let meta = algorithm::select_algorithm_meta(key_meta_model.spec);
+    if !KeyState::PendingImport.eq(&key_meta_model.state) {
+        return Err(ServiceError::StateChange(key_meta_model.state.into()));
+    }
+
+    algorithm::validate_key(key_meta_model.spec, &private_key)?;
 
     let key_pair = utils::encode64(&private_key);
 
-    if meta.key_size != private_key.len() {
+    // reimport is only allowed with the same material
+    let fingerprint = material_fingerprint(key_id, &private_key);
+    if let Some(imported) = &key_meta_model.material_fingerprint {
+        if !imported.eq(&fingerprint) {
+            return Err(ServiceError::BadRequest(format!(
+                "key material is different from the imported, key_id: {}",
+                key_id
+            )));
+        }
+    }
+
+    // consume the token atomically, only one of concurrent imports wins and
+    // the wrapped material can never be replayed
+    let mut conn = rdconn(rd).await?;
+    let consumed: usize = conn.del(&params_key).await?;
+    if consumed == 0 {
         return Err(ServiceError::BadRequest(format!(
-            "key length is invalid, expect: {}, actul: {}",
-            meta.key_size,
-            private_key.len()
+            "import token is consumed or expired, key_id: {}",
+            key_id
         )));
     }
 
+    key_model.key_pair = Some(if KeyType::Symmetric.eq(&key_model.key_type) {
+        json!(SymmtricKeyPair {
+            key_pair,
+            wrapped: false
+        })
+    } else {
+        let public_key =
+            algorithm::derive_key(key_meta_model.spec, &private_key)?;
+        json!(AsymmtricKeyPair {
+            public_key: utils::encode64(&public_key),
+            private_key: key_pair.to_owned(),
+            wrapped: false
+        })
+    });
+
     let mut key_active_model = key_model.clone().into_active_model();
+    key_active_model.key_pair = Set(key_model.wrap_key_pair()?.key_pair);
+    key_repository::update_key(db, &key_active_model).await?;
+    KEY_CACHE
+        .remove(&encode_key!(KEY_CACHE_KEY, key_id.as_str()))
+        .await;
 
-    key_active_model.key_pair =
-        Set(Some(if KeyType::Symmetric.eq(&key_model.key_type) {
-            json!(SymmtricKeyPair { key_pair })
-        } else {
-            let public_key =
-                algorithm::derive_key(key_meta_model.spec, &private_key)?;
-            json!(AsymmtricKeyPair {
-                public_key: utils::encode64(&public_key),
-                private_key: key_pair.to_owned()
-            })
-        }));
+    key_meta_model.state = KeyState::Enabled;
+    key_meta_model.material_fingerprint = Some(fingerprint);
+    key_meta_model.material_expire_at = data
+        .key_material_expire_in
+        .map(|expire_in| (Utc::now() + expire_in).naive_local());
+    key_meta_service::set_key_meta(rd, db, key_meta_model).await?;
+
+    Ok(())
+}
+
+fn material_fingerprint(key_id: &str, material: &[u8]) -> String {
+    hex::encode(openssl::sha::sha256(
+        &[key_id.as_bytes(), material].concat(),
+    ))
+}
+
+// erases imported material, the key waits for the same material again
+pub async fn delete_key_material(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+) -> Result<()> {
+    let meta = get_main_key_meta(rd, db, key_id).await?;
+    if !KeyOrigin::External.eq(&meta.origin) {
+        return Err(ServiceError::Unsupported(format!(
+            "key material is not imported, key_id: {}",
+            key_id
+        )));
+    }
+    match meta.state {
+        KeyState::Enabled | KeyState::Disabled => {
+            erase_key_material(rd, db, meta).await
+        }
+        state => Err(ServiceError::StateChange(state.into())),
+    }
+}
+
+async fn erase_key_material(
+    rd: &RdConn,
+    db: &DbConn,
+    mut meta: KeyMetaModel,
+) -> Result<()> {
+    let key_id = meta.key_id.to_owned();
+    let mut key_active_model = get_version_key(db, &key_id, &meta.version)
+        .await?
+        .into_active_model();
+    key_active_model.key_pair = Set(None);
     key_repository::update_key(db, &key_active_model).await?;
+    KEY_CACHE
+        .remove(&encode_key!(KEY_CACHE_KEY, key_id.as_str()))
+        .await;
 
+    // a key pending deletion keeps its state
+    if !KeyState::PendingDeletion.eq(&meta.state) {
+        meta.state = KeyState::PendingImport;
+    }
+    meta.material_expire_at = None;
+    key_meta_service::set_key_meta(rd, db, meta).await?;
+    tracing::warn!("key material is erased, key_id: {}", key_id);
     Ok(())
 }
 
@@ -363,12 +613,146 @@ pub async fn create_key_version(
     Ok(KeyVersionResult::from(key_meta_new))
 }
 
+pub async fn schedule_key_deletion(
+    rd: &RdConn,
+    db: &DbConn,
+    re: &RotateExecutor,
+    de: &DeletionExecutor,
+    key_id: &str,
+    pending_window_in_days: i64,
+) -> Result<KeyDeletionResult> {
+    if !(7 ..= 30).contains(&pending_window_in_days) {
+        return Err(ServiceError::BadRequest(format!(
+            "pending window must be in [7, 30] days, actual: {}",
+            pending_window_in_days
+        )));
+    }
+    let meta = get_main_key_meta(rd, db, key_id).await?;
+    if KeyState::PendingDeletion.eq(&meta.state) {
+        return Err(ServiceError::StateChange(meta.state.into()));
+    }
+    let deletion_at =
+        (Utc::now() + Duration::days(pending_window_in_days)).naive_local();
+    let key_metas = cache::key_meta::get_key_metas(rd, db, key_id)
+        .await?
+        .into_iter()
+        .map(|mut meta| {
+            meta.state = KeyState::PendingDeletion;
+            meta.deletion_at = Some(deletion_at);
+            meta
+        })
+        .collect_vec();
+    key_meta_service::batch_set_key_meta(rd, db, key_metas).await?;
+    // a key pending deletion is never rotated
+    re.remove(key_id).await?;
+    de.submit(key_id, deletion_at).await?;
+    tracing::warn!(
+        "key deletion is scheduled, key_id: {}, deletion_at: {}",
+        key_id,
+        deletion_at
+    );
+    Ok(KeyDeletionResult {
+        key_id: key_id.to_owned(),
+        state: KeyState::PendingDeletion,
+        deletion_at: Some(deletion_at),
+    })
+}
+
+// the canceled key is disabled and must be enabled explicitly, versions
+// without material are restored to pending import
+pub async fn cancel_key_deletion(
+    rd: &RdConn,
+    db: &DbConn,
+    re: &RotateExecutor,
+    de: &DeletionExecutor,
+    key_id: &str,
+) -> Result<KeyDeletionResult> {
+    let meta = get_main_key_meta(rd, db, key_id).await?;
+    if !KeyState::PendingDeletion.eq(&meta.state) {
+        return Err(ServiceError::StateChange(meta.state.into()));
+    }
+    // the stored rows tell which versions hold material, no need to unwrap
+    let keys = key_repository::select_key(db, key_id).await?;
+    let restored_state =
+        |version: &str| match keys.iter().find(|key| key.version.eq(version)) {
+            Some(key) if key.key_pair.is_some() => KeyState::Disabled,
+            _ => KeyState::PendingImport,
+        };
+    let state = restored_state(&meta.primary_version);
+    let key_metas = cache::key_meta::get_key_metas(rd, db, key_id)
+        .await?
+        .into_iter()
+        .map(|mut meta| {
+            meta.state = restored_state(&meta.version);
+            meta.deletion_at = None;
+            meta
+        })
+        .collect_vec();
+    key_meta_service::batch_set_key_meta(rd, db, key_metas).await?;
+    de.remove(key_id).await?;
+    if meta.rotation_interval > 0 {
+        re.submit(key_id, Duration::seconds(meta.rotation_interval))
+            .await?;
+    }
+    tracing::info!(
+        "key deletion is canceled, key_id: {}, state: {:?}",
+        key_id,
+        state
+    );
+    Ok(KeyDeletionResult {
+        key_id: key_id.to_owned(),
+        state,
+        deletion_at: None,
+    })
+}
+
+// permanently removes the key material, metas and aliases
+async fn purge_key(rd: &RdConn, db: &DbConn, key_id: &str) -> Result<()> {
+    let meta = match get_main_key_meta(rd, db, key_id).await {
+        Ok(meta) => meta,
+        // purged already
+        Err(ServiceError::NotFount(_)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    match meta.deletion_at {
+        Some(deletion_at)
+            if KeyState::PendingDeletion.eq(&meta.state)
+                && deletion_at <= Utc::now().naive_local() => {}
+        _ => {
+            tracing::warn!(
+                "key is not due for deletion, key_id: {}, state: {:?}",
+                key_id,
+                meta.state
+            );
+            return Ok(());
+        }
+    }
+    let txn = db.begin().await?;
+    key_alias_repository::delete_key_aliases(&txn, key_id, vec![]).await?;
+    key_repository::delete_keys(&txn, key_id).await?;
+    key_meta_repository::delete_key_metas(&txn, key_id).await?;
+    txn.commit().await?;
+
+    KEY_CACHE.remove(&encode_key!(KEY_CACHE_KEY, key_id)).await;
+    cache::key_meta::remove_key_meta(rd, key_id).await?;
+    tracing::warn!("key is purged, key_id: {}", key_id);
+    Ok(())
+}
+
 async fn save_key(db: &DbConn, model: &KeyModel) -> Result<()> {
     batch_save_key(db, vec![model.clone()]).await
 }
 
 async fn batch_save_key(db: &DbConn, models: Vec<KeyModel>) -> Result<()> {
-    key_repository::insert_keys(db, models.clone()).await?;
+    key_repository::insert_keys(
+        db,
+        models
+            .clone()
+            .into_iter()
+            .map(KeyModel::wrap_key_pair)
+            .collect::<Result<Vec<KeyModel>>>()?,
+    )
+    .await?;
 
     for key_id in models.into_iter().map(|model| model.key_id).unique() {
         KEY_CACHE.remove(&encode_key!(KEY_CACHE_KEY, key_id)).await;
@@ -376,6 +760,65 @@ async fn batch_save_key(db: &DbConn, models: Vec<KeyModel>) -> Result<()> {
     Ok(())
 }
 
+// only enabled keys export their public key, a disabled or pending deletion
+// key is out of use for verification and encryption alike
+pub async fn get_public_key(
+    rd: &RdConn,
+    db: &DbConn,
+    key_id: &str,
+    query: &KeyPublicKeyQuery,
+) -> Result<KeyPublicKeyResult> {
+    let meta = get_main_key_meta(rd, db, key_id).await?;
+    let alg_meta = algorithm::select_algorithm_meta(meta.spec);
+    if !KeyType::Asymmetric.eq(&alg_meta.key_type) {
+        return Err(ServiceError::BadRequest(format!(
+            "symmetric key has no public key, key_id: {}",
+            key_id
+        )));
+    }
+    if !KeyState::Enabled.eq(&meta.state) {
+        return Err(ServiceError::StateChange(meta.state.into()));
+    }
+    let version = query.version.as_deref().unwrap_or(&meta.primary_version);
+    crypto_service::assert_material_unexpired(
+        &key_meta_service::get_version_key_meta(rd, db, key_id, version)
+            .await?,
+    )?;
+    let key = get_public_keys(db, key_id)
+        .await?
+        .into_iter()
+        .find(|key| key.version.eq(version))
+        .ok_or(ServiceError::NotFount(format!(
+            "key_id is invalid, key_id: {}",
+            key_id
+        )))?;
+    let public_key = key.decode_public_key()?;
+    let algorithms_of = |usage: KeyUsage| {
+        alg_meta
+            .key_algorithms
+            .iter()
+            .filter(|alg| usage.eq(&alg.usage()))
+            .copied()
+            .collect_vec()
+    };
+    Ok(KeyPublicKeyResult {
+        public_key: public_key::encode_public_key(
+            meta.spec,
+            meta.usage,
+            &public_key,
+            query.format,
+            &public_key::key_version_kid(&key.key_id, &key.version),
+        )?,
+        key_id: key.key_id,
+        version: key.version,
+        key_spec: meta.spec,
+        key_usage: meta.usage,
+        format: query.format,
+        signing_algorithms: algorithms_of(KeyUsage::SignAndVerify),
+        encryption_algorithms: algorithms_of(KeyUsage::EncryptAndDecrypt),
+    })
+}
+
 pub async fn get_main_key(
     rd: &RdConn,
     db: &DbConn,
@@ -400,17 +843,64 @@ pub async fn get_version_key(
         )))
 }
 
+// the versions as stored, the private keys stay wrapped so the public keys
+// are readable while the root key is sealed
+pub async fn get_public_keys(
+    db: &DbConn,
+    key_id: &str,
+) -> Result<Vec<KeyModel>> {
+    key_repository::select_key(db, key_id).await
+}
+
 pub async fn get_keys(db: &DbConn, key_id: &str) -> Result<Vec<KeyModel>> {
     let cache_key = encode_key!(KEY_CACHE_KEY, key_id);
     if let Some(version_keys) = KEY_CACHE.get(&cache_key).await {
         Ok(version_keys)
     } else {
-        let ks = key_repository::select_key(db, key_id).await?;
+        let ks = key_repository::select_key(db, key_id)
+            .await?
+            .into_iter()
+            .map(KeyModel::unwrap_key_pair)
+            .collect::<Result<Vec<KeyModel>>>()?;
         KEY_CACHE.insert(cache_key, ks.clone()).await;
         Ok(ks)
     }
 }
 
+// one-shot wrap of the material stored before the root key, rows already
+// wrapped are skipped so it is safe to rerun
+pub async fn migrate_key_material(db: &DbConn) -> Result<usize> {
+    let mut migrated = 0;
+    let mut after = 0;
+    loop {
+        let keys = key_repository::select_keys_after(db, after, 100).await?;
+        after = match keys.last() {
+            Some(key) => key.id,
+            None => break,
+        };
+        for key in keys {
+            if let Some(model) = key.migrate_key_pair()? {
+                let mut key_active_model = model.clone().into_active_model();
+                key_active_model.key_pair = Set(model.key_pair);
+                key_repository::update_key(db, &key_active_model).await?;
+                tracing::info!(
+                    "key material is migrated, key_id: {}, version: {}",
+                    model.key_id,
+                    model.version
+                );
+                migrated += 1;
+            }
+        }
+    }
+    invalidate_key_cache();
+    Ok(migrated)
+}
+
+// drop every cached key material, e.g. when the root key is sealed
+pub fn invalidate_key_cache() {
+    KEY_CACHE.invalidate_all();
+}
+
 #[cfg(test)]
 mod test {
 
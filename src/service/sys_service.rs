@@ -0,0 +1,181 @@
+use std::{
+    fs,
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Context};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::key_service;
+use crate::{
+    common::{
+        configs::env_var_default,
+        errors::{Result, ServiceError},
+        utils,
+    },
+    crypto::{
+        root_key::{self, MemoryRootKey, RootKeyProvider, ROOT_KEY_SIZE},
+        shamir,
+    },
+    pojo::{
+        form::sys::{SysInitBody, SysUnsealBody},
+        result::sys::{SysInitResult, SysStatusResult},
+    },
+};
+
+const ROOT_KEY_CHECK: &[u8] = b"kms root key check";
+const ROOT_KEY_CHECK_AAD: &[u8] = b"kms:sys:seal";
+
+lazy_static! {
+    // shares submitted by operators since the last seal
+    static ref UNSEAL_SHARES: Mutex<Vec<Vec<u8>>> = Mutex::new(vec![]);
+}
+
+// persisted by init, the root key itself is never stored
+#[derive(Serialize, Deserialize)]
+struct SealConfig {
+    shares: u8,
+    threshold: u8,
+    // the check value wrapped by the root key, verifies unsealed root key
+    check: String,
+}
+
+fn seal_file() -> PathBuf {
+    PathBuf::from(env_var_default::<String>(
+        "ROOT_KEY_SEAL_FILE",
+        "./root.seal".to_owned(),
+    ))
+}
+
+fn assert_shamir() -> Result<()> {
+    let provider =
+        env_var_default::<String>("ROOT_KEY_PROVIDER", "file".to_owned());
+    if !"shamir".eq(&provider) {
+        return Err(ServiceError::Unsupported(format!(
+            "seal is unsupported by root key provider: {}",
+            provider
+        )));
+    }
+    Ok(())
+}
+
+fn load_seal_config() -> Result<Option<SealConfig>> {
+    let path = seal_file();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .context(format!("read seal file failed: {:?}", path))?;
+    Ok(Some(serde_json::from_str::<SealConfig>(&content).context(
+        format!("deserialize seal file failed: {:?}", path),
+    )?))
+}
+
+pub async fn status() -> Result<SysStatusResult> {
+    let config = load_seal_config()?;
+    Ok(SysStatusResult {
+        initialized: config.is_some(),
+        sealed: root_key::is_sealed(),
+        shares: config.as_ref().map(|c| c.shares).unwrap_or_default(),
+        threshold: config.as_ref().map(|c| c.threshold).unwrap_or_default(),
+        progress: UNSEAL_SHARES.lock().await.len(),
+    })
+}
+
+pub async fn init(body: &SysInitBody) -> Result<SysInitResult> {
+    assert_shamir()?;
+    let root_key = utils::generate_key(ROOT_KEY_SIZE)?;
+    let shares = shamir::split(&root_key, body.shares, body.threshold)?;
+    let check = MemoryRootKey::new(root_key)?
+        .wrap(ROOT_KEY_CHECK, ROOT_KEY_CHECK_AAD)?;
+
+    let config = serde_json::to_string(&SealConfig {
+        shares: body.shares,
+        threshold: body.threshold,
+        check: utils::encode64(&check),
+    })
+    .context("serialize seal config failed")?;
+
+    // create_new makes the existence check and the write atomic, of two
+    // concurrent inits only one gets shares that unseal the root key
+    let path = seal_file();
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => ServiceError::BadRequest(
+                "root key is already initialized".to_owned(),
+            ),
+            _ => ServiceError::InternalServer(anyhow!(
+                "create seal file failed: {:?}, {}",
+                path,
+                e
+            )),
+        })?;
+    if let Err(e) = file.write_all(config.as_bytes()) {
+        let _ = fs::remove_file(&path);
+        return Err(ServiceError::InternalServer(anyhow!(
+            "write seal file failed: {:?}, {}",
+            path,
+            e
+        )));
+    }
+
+    Ok(SysInitResult {
+        shares: shares.iter().map(|share| utils::encode64(share)).collect(),
+        threshold: body.threshold,
+    })
+}
+
+pub async fn unseal(body: &SysUnsealBody) -> Result<SysStatusResult> {
+    assert_shamir()?;
+    let config = load_seal_config()?.ok_or(ServiceError::BadRequest(
+        "root key is not initialized".to_owned(),
+    ))?;
+    if !root_key::is_sealed() {
+        return status().await;
+    }
+    let share = utils::decode64(&body.share)?;
+    if share.len() != ROOT_KEY_SIZE + 1 {
+        return Err(ServiceError::BadRequest("share is malformed".to_owned()));
+    }
+    {
+        let mut shares = UNSEAL_SHARES.lock().await;
+        if !shares.iter().any(|submitted| submitted[0] == share[0]) {
+            shares.push(share);
+        }
+        if shares.len() >= config.threshold as usize {
+            let combined = shamir::combine(&shares);
+            shares.clear();
+            let root_key = MemoryRootKey::new(combined?)?;
+            root_key
+                .unwrap(&utils::decode64(&config.check)?, ROOT_KEY_CHECK_AAD)
+                .map_err(|_| {
+                    ServiceError::BadRequest(
+                        "shares are invalid, unseal progress is reset"
+                            .to_owned(),
+                    )
+                })?;
+            root_key::install(Arc::new(root_key));
+            tracing::info!("root key is unsealed");
+        }
+    }
+    status().await
+}
+
+pub async fn seal() -> Result<SysStatusResult> {
+    assert_shamir()?;
+    root_key::uninstall();
+    UNSEAL_SHARES.lock().await.clear();
+    // cached keys hold unwrapped material
+    key_service::invalidate_key_cache();
+    tracing::info!("root key is sealed");
+    status().await
+}